@@ -0,0 +1,245 @@
+//! Derive macros of [realm-web-rs](https://github.com/codecrafter404/realm-web-rs), use them through its `derive` feature
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Error, Fields, LitStr, parse_macro_input, spanned::Spanned};
+
+/// # Derive Model
+///
+/// Implements `realm_web_rs::Model` and adds a constant with the document field name of every field,
+/// named like the field in upper case.
+/// Fields named like a constant of `Model` (`data_source`, `database`, `collection`, `id_field` and `fields`) are rejected,
+/// because their constant would shadow it; rename the field and keep the document field name with `#[serde(rename = "...")]`.
+///
+/// ### container attributes
+/// `#[model(data_source = "...", database = "...", collection = "...")]`, all required
+/// ### field attributes
+/// `#[model(id)]` marks the id field, which defaults to the field stored as `_id`
+#[proc_macro_derive(Model, attributes(model))]
+pub fn derive_model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(x) => x.into(),
+        Err(x) => x.to_compile_error().into(),
+    }
+}
+
+/// the constants of `Model`, which the constants of the fields must not shadow
+const MODEL_CONSTS: [&str; 5] = ["DATA_SOURCE", "DATABASE", "COLLECTION", "ID_FIELD", "FIELDS"];
+
+struct Field {
+    ident: syn::Ident,
+    name: String,
+    id: bool,
+}
+
+fn expand(input: DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let fields = match &input.data {
+        Data::Struct(x) => match &x.fields {
+            Fields::Named(x) => &x.named,
+            _ => return Err(Error::new(input.span(), "Model can only be derived for structs with named fields")),
+        },
+        _ => return Err(Error::new(input.span(), "Model can only be derived for structs")),
+    };
+
+    let mut data_source = None;
+    let mut database = None;
+    let mut collection = None;
+    let mut rename_all = None;
+    for attr in &input.attrs {
+        if attr.path().is_ident("model") {
+            attr.parse_nested_meta(|meta| {
+                let value = meta.value()?.parse::<LitStr>()?;
+                if meta.path.is_ident("data_source") {
+                    data_source = Some(value);
+                } else if meta.path.is_ident("database") {
+                    database = Some(value);
+                } else if meta.path.is_ident("collection") {
+                    collection = Some(value);
+                } else {
+                    return Err(meta.error("expected `data_source`, `database` or `collection`"));
+                }
+                Ok(())
+            })?;
+        } else if attr.path().is_ident("serde") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename_all") {
+                    rename_all = serialized_name(&meta)?.or(rename_all.take());
+                } else {
+                    skip_meta(&meta)?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+    let missing = |name: &str| Error::new(Span::call_site(), format!("missing `#[model({} = \"...\")]`", name));
+    let data_source = data_source.ok_or_else(|| missing("data_source"))?;
+    let database = database.ok_or_else(|| missing("database"))?;
+    let collection = collection.ok_or_else(|| missing("collection"))?;
+
+    let mut model_fields = Vec::new();
+    for field in fields {
+        let ident = field.ident.clone().ok_or_else(|| Error::new(field.span(), "expected a named field"))?;
+        let mut name = None;
+        let mut skip = false;
+        let mut id = false;
+        for attr in &field.attrs {
+            if attr.path().is_ident("model") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("id") {
+                        id = true;
+                        Ok(())
+                    } else {
+                        Err(meta.error("expected `id`"))
+                    }
+                })?;
+            } else if attr.path().is_ident("serde") {
+                attr.parse_nested_meta(|meta| {
+                    if meta.path.is_ident("rename") {
+                        name = serialized_name(&meta)?.map(|x| x.value()).or(name.take());
+                    } else if meta.path.is_ident("skip") {
+                        skip = true;
+                    } else if meta.path.is_ident("flatten") {
+                        return Err(meta.error("flattened fields aren't supported by Model"));
+                    } else {
+                        skip_meta(&meta)?;
+                    }
+                    Ok(())
+                })?;
+            }
+        }
+        if skip {
+            continue;
+        }
+        if MODEL_CONSTS.contains(&unraw(&ident).to_uppercase().as_str()) {
+            return Err(Error::new(ident.span(), format!(
+                "the constant of the field `{}` would shadow `Model::{}`, rename the field and keep its name with `#[serde(rename = \"...\")]`",
+                unraw(&ident), unraw(&ident).to_uppercase()
+            )));
+        }
+        let name = match (name, &rename_all) {
+            (Some(x), _) => x,
+            (None, Some(rule)) => rename(&unraw(&ident), rule)?,
+            (None, None) => unraw(&ident),
+        };
+        model_fields.push(Field { ident, name, id });
+    }
+
+    let id_field = match model_fields.iter().filter(|x| x.id).collect::<Vec<_>>().as_slice() {
+        [] => "_id".to_string(),
+        [x] => x.name.clone(),
+        [_, x, ..] => return Err(Error::new(x.ident.span(), "only one field can be marked with `#[model(id)]`")),
+    };
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let names = model_fields.iter().map(|x| &x.name).collect::<Vec<_>>();
+    let consts = model_fields.iter().map(|x| {
+        let name = &x.name;
+        let constant = format_ident!("{}", unraw(&x.ident).to_uppercase());
+        let doc = format!("the document field name of `{}`", unraw(&x.ident));
+        quote! {
+            #[doc = #doc]
+            pub const #constant: &'static str = #name;
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::realm_web_rs::Model for #ident #ty_generics #where_clause {
+            const DATA_SOURCE: &'static str = #data_source;
+            const DATABASE: &'static str = #database;
+            const COLLECTION: &'static str = #collection;
+            const ID_FIELD: &'static str = #id_field;
+            const FIELDS: &'static [&'static str] = &[#(#names),*];
+        }
+
+        #[allow(unused)]
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #(#consts)*
+        }
+    })
+}
+
+/// the value of `rename = "..."`, or of `serialize` in `rename(serialize = "...", deserialize = "...")`
+fn serialized_name(meta: &syn::meta::ParseNestedMeta) -> Result<Option<LitStr>, Error> {
+    if !meta.input.peek(syn::token::Paren) {
+        return Ok(Some(meta.value()?.parse::<LitStr>()?));
+    }
+    let mut name = None;
+    meta.parse_nested_meta(|meta| {
+        if meta.path.is_ident("serialize") {
+            name = Some(meta.value()?.parse::<LitStr>()?);
+        } else {
+            skip_meta(&meta)?;
+        }
+        Ok(())
+    })?;
+    Ok(name)
+}
+
+/// consumes the value of a serde attribute, which isn't relevant
+fn skip_meta(meta: &syn::meta::ParseNestedMeta) -> Result<(), Error> {
+    if meta.input.peek(syn::Token![=]) {
+        meta.value()?.parse::<syn::Expr>()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|meta| skip_meta(&meta))?;
+    }
+    Ok(())
+}
+
+fn unraw(ident: &syn::Ident) -> String {
+    let ident = ident.to_string();
+    ident.strip_prefix("r#").map(|x| x.to_string()).unwrap_or(ident)
+}
+
+/// applies a serde `rename_all` rule to a snake case field name
+fn rename(field: &str, rule: &LitStr) -> Result<String, Error> {
+    let words = field.split('_').filter(|x| !x.is_empty());
+    let capitalize = |x: &str| {
+        let mut chars = x.chars();
+        chars.next().map(|c| c.to_uppercase().chain(chars).collect::<String>()).unwrap_or_default()
+    };
+    Ok(match rule.value().as_str() {
+        "lowercase" => field.to_lowercase(),
+        "UPPERCASE" => field.to_uppercase(),
+        "snake_case" => field.to_string(),
+        "SCREAMING_SNAKE_CASE" => field.to_uppercase(),
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.replace('_', "-").to_uppercase(),
+        "PascalCase" => words.map(capitalize).collect(),
+        "camelCase" => words.enumerate().map(|(i, x)| if i == 0 { x.to_string() } else { capitalize(x) }).collect(),
+        _ => return Err(Error::new(rule.span(), "unknown rename_all rule")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::expand;
+
+    #[test]
+    fn rejects_fields_shadowing_model_consts() {
+        for field in ["collection", "database", "data_source", "id_field", "fields"] {
+            let ident = syn::Ident::new(field, proc_macro2::Span::call_site());
+            let error = expand(parse_quote! {
+                #[model(data_source = "mongodb-atlas", database = "shop", collection = "users")]
+                struct User {
+                    #ident: String,
+                }
+            }).unwrap_err();
+            assert!(error.to_string().contains(&format!("would shadow `Model::{}`", field.to_uppercase())), "{}", error);
+        }
+    }
+
+    #[test]
+    fn skipped_fields_may_shadow_model_consts() {
+        assert!(expand(parse_quote! {
+            #[model(data_source = "mongodb-atlas", database = "shop", collection = "users")]
+            struct User {
+                #[serde(skip)]
+                collection: String,
+            }
+        }).is_ok());
+    }
+}
@@ -0,0 +1,264 @@
+use std::future::IntoFuture;
+
+use bson::Document;
+use futures_util::Stream;
+use serde::de::DeserializeOwned;
+
+use crate::{
+    Client, Collection, Error, Update, transport::BoxFuture, pagination, typed,
+    FindRequest, FindResponse, InsertRequest, InsertResponse, UpdateRequest, UpdateResponse,
+    ReplaceRequest, ReplaceResponse, DeleteRequest, DeleteResponse, AggregationRequest, AggregationResponse,
+};
+
+/// implements `send` and `IntoFuture` for an action builder,
+/// `$idempotent` tells from the request, whether it may be retried without side effects
+macro_rules! action {
+    ($builder:ident, $action:literal, $response:ty, $idempotent:expr) => {
+        impl<'a> $builder<'a> {
+            /// sends the request
+            pub async fn send(self) -> Result<$response, Error> {
+                let idempotent = ($idempotent)(&self.req);
+                self.client.send_action($action, &self.req, idempotent).await
+            }
+        }
+        impl<'a> IntoFuture for $builder<'a> {
+            type Output = Result<$response, Error>;
+            type IntoFuture = BoxFuture<'a, Self::Output>;
+
+            fn into_future(self) -> Self::IntoFuture {
+                Box::pin(self.send())
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Creates the request builders of all data api actions, see [Client::action]
+///
+/// The builders are sent by either calling `send` or awaiting them directly.
+pub struct Actions<'a> {
+    pub(crate) client: &'a Client,
+}
+
+impl<'a> Actions<'a> {
+    /// # Find a Single Document
+    ///
+    /// see [Client::find_one]
+    pub fn find_one(self, collection: Collection) -> FindOne<'a> {
+        FindOne { client: self.client, req: FindRequest::new(collection) }
+    }
+    /// # Find Multiple Documents
+    ///
+    /// see [Client::find]
+    pub fn find(self, collection: Collection) -> Find<'a> {
+        Find { client: self.client, req: FindRequest::new(collection) }
+    }
+    /// # Insert a Single Document
+    ///
+    /// see [Client::insert_one]
+    pub fn insert_one(self, collection: Collection, document: Document) -> InsertOne<'a> {
+        InsertOne { client: self.client, req: InsertRequest { collection, document: Some(document), documents: None } }
+    }
+    /// # Insert Multiple Documents
+    ///
+    /// see [Client::insert]
+    pub fn insert(self, collection: Collection, documents: Vec<Document>) -> InsertMany<'a> {
+        InsertMany { client: self.client, req: InsertRequest { collection, document: None, documents: Some(documents) } }
+    }
+    /// # Update a Single Document
+    ///
+    /// see [Client::update_one]
+    pub fn update_one(self, collection: Collection, filter: impl Into<Document>, update: impl Into<Update>) -> UpdateOne<'a> {
+        UpdateOne { client: self.client, req: UpdateRequest { collection, filter: filter.into(), update: update.into(), upsert: None } }
+    }
+    /// # Update Multiple Documents
+    ///
+    /// see [Client::update]
+    pub fn update(self, collection: Collection, filter: impl Into<Document>, update: impl Into<Update>) -> UpdateMany<'a> {
+        UpdateMany { client: self.client, req: UpdateRequest { collection, filter: filter.into(), update: update.into(), upsert: None } }
+    }
+    /// # Replace a Single Document
+    ///
+    /// see [Client::replace_one]
+    pub fn replace_one(self, collection: Collection, filter: impl Into<Document>, replacement: Document) -> ReplaceOne<'a> {
+        ReplaceOne { client: self.client, req: ReplaceRequest { collection, filter: filter.into(), replacement, upsert: None } }
+    }
+    /// # Delete a Single Document
+    ///
+    /// see [Client::delete_one]
+    pub fn delete_one(self, collection: Collection, filter: impl Into<Document>) -> DeleteOne<'a> {
+        DeleteOne { client: self.client, req: DeleteRequest { collection, filter: filter.into() } }
+    }
+    /// # Delete Multiple Documents
+    ///
+    /// see [Client::delete]
+    pub fn delete(self, collection: Collection, filter: impl Into<Document>) -> DeleteMany<'a> {
+        DeleteMany { client: self.client, req: DeleteRequest { collection, filter: filter.into() } }
+    }
+    /// # Run an Aggregation Pipeline
+    ///
+    /// see [Client::aggregate]
+    pub fn aggregate(self, collection: Collection, pipeline: impl Into<Vec<Document>>) -> Aggregate<'a> {
+        Aggregate { client: self.client, req: AggregationRequest { collection, pipeline: pipeline.into() } }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Builder of the findOne action
+pub struct FindOne<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: FindRequest,
+}
+impl<'a> FindOne<'a> {
+    /// the [MongoDB Query Filter](https://www.mongodb.com/docs/manual/tutorial/query-documents/), defaults to all documents
+    pub fn filter(mut self, filter: impl Into<Document>) -> Self {
+        self.req.filter = Some(filter.into());
+        self
+    }
+    /// the [MongoDB Query Projection](https://www.mongodb.com/docs/manual/tutorial/project-fields-from-query-results/)
+    pub fn projection(mut self, projection: Document) -> Self {
+        self.req.projection = Some(projection);
+        self
+    }
+}
+action!(FindOne, "findOne", FindResponse, |_| true);
+
+#[derive(Debug, Clone)]
+/// Builder of the find action
+pub struct Find<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: FindRequest,
+}
+impl<'a> Find<'a> {
+    /// the [MongoDB Query Filter](https://www.mongodb.com/docs/manual/tutorial/query-documents/), defaults to all documents
+    pub fn filter(mut self, filter: impl Into<Document>) -> Self {
+        self.req.filter = Some(filter.into());
+        self
+    }
+    /// the [MongoDB Query Projection](https://www.mongodb.com/docs/manual/tutorial/project-fields-from-query-results/)
+    pub fn projection(mut self, projection: Document) -> Self {
+        self.req.projection = Some(projection);
+        self
+    }
+    /// the [MongoDB Sort Expression](https://www.mongodb.com/docs/manual/reference/operator/aggregation/sort/)
+    pub fn sort(mut self, sort: Document) -> Self {
+        self.req.sort = Some(sort);
+        self
+    }
+    /// the maximum number of returned documents, up to 50,000
+    pub fn limit(mut self, limit: i32) -> Self {
+        self.req.limit = Some(limit);
+        self
+    }
+    /// the number of matched documents to skip
+    pub fn skip(mut self, skip: i32) -> Self {
+        self.req.skip = Some(skip);
+        self
+    }
+}
+impl<'a> Find<'a> {
+    /// pages through all matching documents, requesting `page_size` documents at a time
+    ///
+    /// The limit and skip apply to the whole stream, see [Client::find_stream]
+    pub fn stream(self, page_size: i32) -> impl Stream<Item = Result<Document, Error>> + 'a {
+        pagination::find_stream(self.client, self.req, page_size)
+    }
+}
+action!(Find, "find", FindResponse, |_| true);
+
+#[derive(Debug, Clone)]
+/// Builder of the insertOne action
+pub struct InsertOne<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: InsertRequest,
+}
+action!(InsertOne, "insertOne", InsertResponse, |_| false);
+
+#[derive(Debug, Clone)]
+/// Builder of the insertMany action
+pub struct InsertMany<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: InsertRequest,
+}
+action!(InsertMany, "insertMany", InsertResponse, |_| false);
+
+#[derive(Debug, Clone)]
+/// Builder of the updateOne action
+pub struct UpdateOne<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: UpdateRequest,
+}
+impl<'a> UpdateOne<'a> {
+    /// inserts a new document, if no document matches the filter
+    pub fn upsert(mut self, upsert: bool) -> Self {
+        self.req.upsert = Some(upsert);
+        self
+    }
+}
+action!(UpdateOne, "updateOne", UpdateResponse, |_| false);
+
+#[derive(Debug, Clone)]
+/// Builder of the updateMany action
+pub struct UpdateMany<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: UpdateRequest,
+}
+impl<'a> UpdateMany<'a> {
+    /// inserts a new document, if no document matches the filter
+    pub fn upsert(mut self, upsert: bool) -> Self {
+        self.req.upsert = Some(upsert);
+        self
+    }
+}
+action!(UpdateMany, "updateMany", UpdateResponse, |_| false);
+
+#[derive(Debug, Clone)]
+/// Builder of the replaceOne action
+pub struct ReplaceOne<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: ReplaceRequest,
+}
+impl<'a> ReplaceOne<'a> {
+    /// inserts the replacement, if no document matches the filter
+    pub fn upsert(mut self, upsert: bool) -> Self {
+        self.req.upsert = Some(upsert);
+        self
+    }
+}
+action!(ReplaceOne, "replaceOne", ReplaceResponse, |req: &ReplaceRequest| req.upsert != Some(true));
+
+#[derive(Debug, Clone)]
+/// Builder of the deleteOne action
+pub struct DeleteOne<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: DeleteRequest,
+}
+action!(DeleteOne, "deleteOne", DeleteResponse, |_| false);
+
+#[derive(Debug, Clone)]
+/// Builder of the deleteMany action
+pub struct DeleteMany<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: DeleteRequest,
+}
+action!(DeleteMany, "deleteMany", DeleteResponse, |_| true);
+
+#[derive(Debug, Clone)]
+/// Builder of the aggregate action
+pub struct Aggregate<'a> {
+    pub(crate) client: &'a Client,
+    pub(crate) req: AggregationRequest,
+}
+impl<'a> Aggregate<'a> {
+    /// appends a stage to the pipeline
+    pub fn stage(mut self, stage: Document) -> Self {
+        self.req.pipeline.push(stage);
+        self
+    }
+    /// sends the request and converts the output documents into `T`
+    pub async fn send_as<T: DeserializeOwned>(self) -> Result<Vec<T>, Error> {
+        let res = self.send().await?;
+        res.documents.into_iter().map(typed::from_document).collect()
+    }
+}
+action!(Aggregate, "aggregate", AggregationResponse, |_| true);
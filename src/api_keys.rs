@@ -0,0 +1,193 @@
+use http::{Method, header::{HeaderName, HeaderValue}};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::{Authentication, Credentials, Error, HttpRequest, User, auth::bearer_headers};
+
+#[derive(Debug, Clone, Copy)]
+/// Manages the api keys of a logged in user, see [User::api_key_auth]
+///
+/// The requests are authenticated with the refresh token of the user.
+pub struct ApiKeyAuth<'a> {
+    user: &'a User,
+}
+
+impl User {
+    /// the api keys of the user
+    pub fn api_key_auth(&self) -> ApiKeyAuth<'_> {
+        ApiKeyAuth { user: self }
+    }
+}
+
+#[allow(unused)]
+impl<'a> ApiKeyAuth<'a> {
+    /// # Create an Api Key
+    ///
+    /// the returned key contains the secret, which can't be fetched later
+    pub async fn create(&self, name: &str) -> Result<ApiKey, Error> {
+        self.send(Method::POST, "", Some(&CreateApiKeyRequest { name })).await
+    }
+    /// # Fetch an Api Key
+    pub async fn fetch(&self, key_id: &str) -> Result<ApiKey, Error> {
+        self.send(Method::GET, &format!("/{}", key_id), None::<&()>).await
+    }
+    /// # Fetch all Api Keys
+    pub async fn fetch_all(&self) -> Result<Vec<ApiKey>, Error> {
+        self.send(Method::GET, "", None::<&()>).await
+    }
+    /// # Enable an Api Key
+    pub async fn enable(&self, key_id: &str) -> Result<(), Error> {
+        self.send_empty(Method::PUT, &format!("/{}/enable", key_id)).await
+    }
+    /// # Disable an Api Key
+    ///
+    /// disabled keys can't be used to log in until they are enabled again
+    pub async fn disable(&self, key_id: &str) -> Result<(), Error> {
+        self.send_empty(Method::PUT, &format!("/{}/disable", key_id)).await
+    }
+    /// # Delete an Api Key
+    pub async fn delete(&self, key_id: &str) -> Result<(), Error> {
+        self.send_empty(Method::DELETE, &format!("/{}", key_id)).await
+    }
+
+    async fn send<Req: Serialize, Res: DeserializeOwned>(&self, method: Method, path: &str, req: Option<&Req>) -> Result<Res, Error> {
+        let body = self.request(method, path, req).await?;
+        serde_json::from_slice(&body).map_err(|x| Error::Deserialization(format!("{:?}", x)))
+    }
+    async fn send_empty(&self, method: Method, path: &str) -> Result<(), Error> {
+        self.request(method, path, None::<&()>).await.map(|_| ())
+    }
+    /// sends the request to `/auth/api_keys<path>` and returns the response body
+    async fn request<Req: Serialize>(&self, method: Method, path: &str, req: Option<&Req>) -> Result<Vec<u8>, Error> {
+        let refresh_token = self.user.refresh_token()
+            .ok_or_else(|| Error::SessionExpired { status: None, error: "No refresh token".into() })?;
+        self.user.app.resolve_location().await?;
+        let mut headers = bearer_headers(&refresh_token)?;
+        let body = match req {
+            Some(req) => {
+                headers.append(HeaderName::from_static("content-type"), HeaderValue::from_static("application/json"));
+                Some(serde_json::to_vec(req).map_err(|x| Error::Serialization(format!("{:?}", x)))?)
+            },
+            None => None,
+        };
+
+        let res = self.user.app.transport.send(HttpRequest {
+            method,
+            url: format!("{}/auth/api_keys{}", self.user.app.client_api_url(), path),
+            headers,
+            body,
+        }).await?;
+
+        if !res.status.is_success(){
+            return Err(Error::from_response(&res))
+        }
+        Ok(res.body)
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An api key of a user
+pub struct ApiKey {
+    #[serde(rename = "_id")]
+    pub id: String,
+    pub name: String,
+    pub disabled: bool,
+    /// the secret, only present in the response of [ApiKeyAuth::create]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+impl ApiKey {
+    /// the credentials to log in with the key, none if the secret isn't known
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.key.clone().map(Credentials::ApiKey)
+    }
+    /// the authentication of data api requests with the `apiKey` header, none if the secret isn't known
+    pub fn authentication(&self) -> Option<Authentication> {
+        self.key.clone().map(Authentication::ApiKey)
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize)]
+struct CreateApiKeyRequest<'a> {
+    name: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures_executor::block_on;
+    use http::Method;
+    use serde_json::json;
+
+    use crate::{App, Credentials, Error, Transport, User};
+    use crate::scripted::{Scripted, header, json_body};
+
+    fn log_in(transport: &Arc<Scripted>) -> User {
+        let app = App::new().application_id("app").transport(transport.clone() as Arc<dyn Transport>).build();
+        transport.respond(200, r#"{"access_token":"access","refresh_token":"refresh","user_id":"u","device_id":"d"}"#);
+        block_on(app.log_in(Credentials::anonymous())).unwrap()
+    }
+
+    #[test]
+    fn requests_are_sent_with_the_refresh_token() {
+        let transport = Scripted::new();
+        let user = log_in(&transport);
+        transport
+            .respond(201, r#"{"_id":"k1","name":"ci","disabled":false,"key":"secret"}"#)
+            .respond(200, r#"{"_id":"k1","name":"ci","disabled":false}"#)
+            .respond(200, r#"[{"_id":"k1","name":"ci","disabled":false},{"_id":"k2","name":"cd","disabled":true}]"#)
+            .respond(204, "")
+            .respond(204, "")
+            .respond(204, "");
+        let keys = user.api_key_auth();
+        block_on(async {
+            let key = keys.create("ci").await.unwrap();
+            assert_eq!(key.key.as_deref(), Some("secret"));
+            assert!(matches!(key.credentials(), Some(Credentials::ApiKey(x)) if x == "secret"));
+            let key = keys.fetch("k1").await.unwrap();
+            assert!(key.key.is_none() && key.credentials().is_none());
+            let all = keys.fetch_all().await.unwrap();
+            assert_eq!(all.iter().map(|x| (x.id.as_str(), x.disabled)).collect::<Vec<_>>(), [("k1", false), ("k2", true)]);
+            keys.enable("k1").await.unwrap();
+            keys.disable("k1").await.unwrap();
+            keys.delete("k1").await.unwrap();
+        });
+
+        let requests = transport.requests();
+        let expected = [
+            (Method::POST, "", Some(json!({"name": "ci"}))),
+            (Method::GET, "/k1", None),
+            (Method::GET, "", None),
+            (Method::PUT, "/k1/enable", None),
+            (Method::PUT, "/k1/disable", None),
+            (Method::DELETE, "/k1", None),
+        ];
+        assert_eq!(requests.len(), expected.len() + 1);
+        for (request, (method, path, body)) in requests[1..].iter().zip(expected) {
+            assert_eq!(request.method, method);
+            assert_eq!(request.url, format!("https://realm.mongodb.com/api/client/v2.0/auth/api_keys{}", path));
+            assert_eq!(header(request, "authorization"), Some("Bearer refresh"));
+            assert_eq!(header(request, "content-type"), body.as_ref().map(|_| "application/json"));
+            match body {
+                Some(body) => assert_eq!(json_body(request), body),
+                None => assert!(request.body.is_none()),
+            }
+        }
+    }
+
+    #[test]
+    fn logged_out_users_send_no_request() {
+        let transport = Scripted::new();
+        let user = log_in(&transport);
+        transport.respond(204, "");
+        block_on(user.log_out()).unwrap();
+        let requests = transport.requests().len();
+
+        let res = block_on(user.api_key_auth().fetch_all());
+        assert!(matches!(res, Err(Error::SessionExpired { .. })), "{:?}", res);
+        assert_eq!(transport.requests().len(), requests);
+    }
+}
@@ -0,0 +1,750 @@
+use std::sync::{Arc, RwLock};
+
+use builder_pattern::Builder;
+use http::{Method, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::{Serialize, Deserialize};
+
+use crate::{Client, ApiVersion, Error, Transport, HttpRequest, Location, SessionStorage, User, location::LocationCache};
+
+#[derive(Builder, Debug, Clone)]
+/// An Atlas App Services application, used to log users in
+pub struct App {
+    #[into]
+    /// The application id, which is inserted into the query url
+    pub application_id: String,
+    #[into]
+    #[default(None)]
+    /// should be none, if deployed globally
+    /// or <Region>.<Cloud>
+    pub deployment_region: Option<String>,
+    #[into]
+    #[default(None)]
+    /// overrides the hostname of all requests, e.g. for a private endpoint or a local server like `http://localhost:8080`
+    pub base_url: Option<String>,
+    #[default(false)]
+    /// queries the location of the app before the first request and uses its hostname instead of the deployment region,
+    /// ignored if a base url is set
+    pub discover_location: bool,
+    #[cfg_attr(feature = "reqwest", default(crate::transport::default_transport()))]
+    /// sends the http requests, defaults to [ReqwestTransport](crate::ReqwestTransport) if the `reqwest` feature is enabled
+    pub transport: Arc<dyn Transport>,
+    #[into]
+    #[default(None)]
+    /// persists the sessions of the logged in users, see [App::restore]
+    pub storage: Option<Arc<dyn SessionStorage>>,
+    #[hidden]
+    #[default(Default::default())]
+    /// the sessions of the logged in users, the current user first; shared between all clones
+    users: Arc<RwLock<Vec<Session>>>,
+    #[hidden]
+    #[default(Default::default())]
+    /// the discovered location; shared between all clones and clients
+    location: LocationCache,
+}
+
+#[allow(unused)]
+impl App {
+    /// gets base url https://realm.mongodb.com/api/client/v2.0/app/<App ID>
+    pub(crate) fn get_url(&self) -> String {
+        format!("{}/app/{}", self.client_api_url(), self.application_id)
+    }
+    /// gets the client api url https://realm.mongodb.com/api/client/v2.0
+    pub(crate) fn client_api_url(&self) -> String {
+        self.location.client_api_url(&self.base_url, &self.deployment_region)
+    }
+
+    /// # Location
+    ///
+    /// the discovered location of the app, none until the first request if `discover_location` is set
+    pub fn location(&self) -> Option<Location> {
+        self.location.get()
+    }
+    /// discovers the location of the app, if enabled and not known yet
+    pub(crate) async fn resolve_location(&self) -> Result<(), Error> {
+        if self.discover_location && self.base_url.is_none() {
+            self.location.resolve(&self.application_id, &self.deployment_region, self.transport.as_ref()).await?;
+        }
+        Ok(())
+    }
+
+    /// # Log in a user
+    ///
+    /// Logs in against the auth provider of the given [Credentials], the user becomes the current user of the app.
+    /// The user can be passed to [App::client] or [Client::as_user].
+    /// With a [SessionStorage], the tokens are stored.
+    pub async fn log_in(
+        &self,
+        credentials: Credentials
+    ) -> Result<User, Error> {
+        self.resolve_location().await?;
+        let mut header_map = HeaderMap::new();
+        header_map.append(HeaderName::from_static("content-type"), HeaderValue::from_static("application/json"));
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("application/json"));
+
+        let res = self.transport.send(HttpRequest {
+            method: Method::POST,
+            url: format!("{}/auth/providers/{}/login", self.get_url(), credentials.provider()),
+            headers: header_map,
+            body: Some(serde_json::to_vec(&credentials.payload()).map_err(|x| Error::Serialization(format!("{:?}", x)))?),
+        }).await?;
+
+        if !res.status.is_success(){
+            return Err(Error::from_response(&res))
+        }
+
+        let tokens = serde_json::from_slice::<Tokens>(&res.body).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+        let user_id = tokens.user_id.clone().ok_or_else(|| Error::Deserialization("The login response has no user_id".into()))?;
+        if let Some(storage) = &self.storage {
+            storage.set(&self.application_id, &user_id, &tokens)?;
+        }
+
+        let session = {
+            let mut users = self.users.write().unwrap();
+            let session = match users.iter().position(|x| x.user_id().as_deref() == Some(user_id.as_str())) {
+                Some(i) => {
+                    let session = users.remove(i);
+                    session.set_tokens(tokens);
+                    session
+                },
+                None => self.bind(tokens.into()),
+            };
+            users.insert(0, session.clone());
+            session
+        };
+        self.store_user_ids()?;
+        Ok(User { app: self.clone(), id: user_id, session })
+    }
+
+    /// # Current user
+    ///
+    /// the logged in user, who logged in or got switched to last; logged out users are skipped
+    pub fn current_user(&self) -> Option<User> {
+        self.all_users().into_iter().find(|x| x.is_logged_in())
+    }
+    /// # All users
+    ///
+    /// the users of the app, the current user first and the logged out users last
+    pub fn all_users(&self) -> Vec<User> {
+        self.users.read().unwrap().iter()
+            .filter_map(|session| Some(User { app: self.clone(), id: session.user_id()?, session: session.clone() }))
+            .collect()
+    }
+    /// # Switch the current user
+    ///
+    /// makes the user with the id the current user, fails if the user isn't logged in to this app
+    pub fn switch_user(&self, user_id: &str) -> Result<User, Error> {
+        let user = {
+            let mut users = self.users.write().unwrap();
+            let i = users.iter().position(|x| x.user_id().as_deref() == Some(user_id) && x.can_refresh())
+                .ok_or_else(|| Error::Auth { status: None, error: format!("The user {} isn't logged in", user_id), error_code: None })?;
+            let session = users.remove(i);
+            users.insert(0, session.clone());
+            User { app: self.clone(), id: user_id.to_string(), session }
+        };
+        self.store_user_ids()?;
+        Ok(user)
+    }
+    /// # Remove a user
+    ///
+    /// Logs the user out and forgets its tokens, the other users stay logged in.
+    /// If its session couldn't be revoked because of an other reason than an expired session, the user is kept.
+    pub async fn remove_user(&self, user_id: &str) -> Result<(), Error> {
+        let session = self.users.read().unwrap().iter().find(|x| x.user_id().as_deref() == Some(user_id)).cloned();
+        if let Some(session) = session {
+            self.resolve_location().await?;
+            match session.revoke(&self.client_api_url(), self.transport.as_ref()).await {
+                Err(x) if !x.is_auth() => return Err(x),
+                _ => {},
+            }
+        }
+        self.forget_user(user_id)
+    }
+    /// moves the logged out user behind the logged in users
+    pub(crate) fn demote_user(&self, user_id: &str) -> Result<(), Error> {
+        {
+            let mut users = self.users.write().unwrap();
+            if let Some(i) = users.iter().position(|x| x.user_id().as_deref() == Some(user_id)) {
+                let session = users.remove(i);
+                let end = users.iter().position(|x| !x.can_refresh()).unwrap_or(users.len());
+                users.insert(end, session);
+            }
+        }
+        self.store_user_ids()
+    }
+    /// removes the user and its stored tokens without logging it out
+    pub(crate) fn forget_user(&self, user_id: &str) -> Result<(), Error> {
+        self.users.write().unwrap().retain(|x| x.user_id().as_deref() != Some(user_id));
+        if let Some(storage) = &self.storage {
+            storage.remove(&self.application_id, user_id)?;
+        }
+        self.store_user_ids()
+    }
+
+    /// # Restore the current session
+    ///
+    /// Loads the stored users from the [SessionStorage] and returns the session of the current user,
+    /// none if there is no storage or no stored user is logged in.
+    pub fn current_session(&self) -> Result<Option<Session>, Error> {
+        self.load_users()?;
+        Ok(self.current_user().map(|x| x.session))
+    }
+
+    /// # Restore the current client
+    ///
+    /// creates a [Client] for the session of the current user, see [App::current_session]
+    pub fn restore(&self) -> Result<Option<Client>, Error> {
+        Ok(self.current_session()?.map(|x| self.client(x)))
+    }
+
+    /// adds the stored users, which aren't logged in yet
+    fn load_users(&self) -> Result<(), Error> {
+        let storage = match &self.storage {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let mut users = self.users.write().unwrap();
+        for user_id in storage.user_ids(&self.application_id)? {
+            if users.iter().any(|x| x.user_id().as_deref() == Some(user_id.as_str())) {
+                continue;
+            }
+            if let Some(mut tokens) = storage.get(&self.application_id, &user_id)? {
+                tokens.user_id = Some(user_id);
+                users.push(self.bind(tokens.into()));
+            }
+        }
+        Ok(())
+    }
+    /// stores the order of the users
+    fn store_user_ids(&self) -> Result<(), Error> {
+        if let Some(storage) = &self.storage {
+            let user_ids = self.users.read().unwrap().iter().filter_map(|x| x.user_id()).collect::<Vec<_>>();
+            storage.set_user_ids(&self.application_id, &user_ids)?;
+        }
+        Ok(())
+    }
+
+    /// creates a data api [Client] for this application, which authenticates using the given session
+    ///
+    /// With a [SessionStorage], refreshed access tokens are stored.
+    pub fn client(&self, session: impl Into<Session>) -> Client {
+        let mut client = Client::new()
+            .application_id(self.application_id.clone())
+            .authentication(Authentication::Bearer(self.bind(session.into())))
+            .deployment_region(self.deployment_region.clone())
+            .base_url(self.base_url.clone())
+            .discover_location(self.discover_location)
+            .api_version(ApiVersion::v1)
+            .transport(self.transport.clone())
+            .build();
+        client.location = self.location.clone();
+        client
+    }
+
+    /// stores the refreshed tokens of the session in the storage of the app
+    fn bind(&self, mut session: Session) -> Session {
+        if let Some(storage) = &self.storage {
+            session.storage = Some((storage.clone(), self.application_id.clone()));
+        }
+        session
+    }
+}
+
+/// gets the client api url https://<Region>.<Cloud>.realm.mongodb.com/api/client/v2.0
+pub(crate) fn get_client_api_url(deployment_region: &Option<String>) -> String {
+    format!(
+        "https://{}realm.mongodb.com/api/client/v2.0",
+        match deployment_region {
+            Some(x) => format!("{}.", x),
+            None => "".into()
+        }
+    )
+}
+
+/// the headers of a request to the client api, authenticated with the given access or refresh token
+pub(crate) fn bearer_headers(token: &str) -> Result<HeaderMap, Error> {
+    let mut header_map = HeaderMap::new();
+    header_map.append(HeaderName::from_static("authorization"), HeaderValue::from_str(&format!("Bearer {}", token)).map_err(|x| Error::Auth { status: None, error: format!("Invalid authentication header: {:?}", x), error_code: None })?);
+    header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("application/json"));
+    Ok(header_map)
+}
+
+#[derive(Debug, Clone)]
+/// How the data api requests are authenticated
+pub enum Authentication {
+    /// authentication using the `apiKey` header
+    ApiKey(String),
+    /// authentication using the `Authorization: Bearer` header with the access token of a logged in user
+    ///
+    /// The access token gets refreshed automatically, if a refresh token is present
+    Bearer(Session),
+}
+
+impl Authentication {
+    /// appends the authentication header to the given header map
+    pub(crate) fn append_headers(&self, header_map: &mut HeaderMap) -> Result<(), Error> {
+        let (name, value) = match self {
+            Authentication::ApiKey(key) => (HeaderName::from_static("apikey"), key.clone()),
+            Authentication::Bearer(session) => (HeaderName::from_static("authorization"), format!("Bearer {}", session.access_token())),
+        };
+        header_map.append(name, HeaderValue::from_str(&value).map_err(|x| Error::Auth { status: None, error: format!("Invalid authentication header: {:?}", x), error_code: None })?);
+        Ok(())
+    }
+}
+
+impl From<Tokens> for Authentication {
+    fn from(tokens: Tokens) -> Self {
+        Authentication::Bearer(tokens.into())
+    }
+}
+
+impl From<Session> for Authentication {
+    fn from(session: Session) -> Self {
+        Authentication::Bearer(session)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The tokens of a logged in user, shared between all clones
+///
+/// Refreshing the access token of one clone updates all of them.
+pub struct Session {
+    tokens: Arc<RwLock<Tokens>>,
+    /// the storage and application id the tokens are persisted in
+    storage: Option<(Arc<dyn SessionStorage>, String)>,
+}
+
+impl Session {
+    /// creates a session from an access/refresh token pair
+    pub fn new(access_token: impl Into<String>, refresh_token: Option<String>) -> Self {
+        Tokens {
+            access_token: access_token.into(),
+            refresh_token,
+            user_id: None,
+            device_id: None,
+        }.into()
+    }
+    /// a snapshot of the current tokens
+    pub fn tokens(&self) -> Tokens {
+        self.tokens.read().unwrap().clone()
+    }
+    /// the current access token
+    pub fn access_token(&self) -> String {
+        self.tokens.read().unwrap().access_token.clone()
+    }
+    /// true, if the session holds a refresh token
+    pub fn can_refresh(&self) -> bool {
+        self.tokens.read().unwrap().refresh_token.is_some()
+    }
+    /// the id of the user, if known
+    pub fn user_id(&self) -> Option<String> {
+        self.tokens.read().unwrap().user_id.clone()
+    }
+    /// replaces the tokens of all clones
+    pub(crate) fn set_tokens(&self, tokens: Tokens) {
+        *self.tokens.write().unwrap() = tokens;
+    }
+
+    /// revokes the refresh token, so the session can't be refreshed anymore
+    pub(crate) async fn revoke(
+        &self,
+        client_api_url: &str,
+        transport: &dyn Transport
+    ) -> Result<(), Error> {
+        let refresh_token = match self.tokens.read().unwrap().refresh_token.clone() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let res = transport.send(HttpRequest {
+            method: Method::DELETE,
+            url: format!("{}/auth/session", client_api_url),
+            headers: bearer_headers(&refresh_token)?,
+            body: None,
+        }).await?;
+
+        if !res.status.is_success(){
+            return Err(Error::from_response(&res))
+        }
+        self.tokens.write().unwrap().refresh_token = None;
+        Ok(())
+    }
+    /// stores the current tokens, if the session is bound to a storage
+    pub(crate) fn persist(&self) -> Result<(), Error> {
+        let tokens = self.tokens();
+        if let (Some((storage, app_id)), Some(user_id)) = (&self.storage, &tokens.user_id) {
+            storage.set(app_id, user_id, &tokens)?;
+        }
+        Ok(())
+    }
+
+    /// # Refresh the access token
+    ///
+    /// Requests a new access token using the refresh token at the client api url,
+    /// e.g. https://realm.mongodb.com/api/client/v2.0
+    /// Fails with [Error::SessionExpired], if the refresh token is missing or got rejected,
+    /// other failures of the refresh request are returned as they are.
+    pub async fn refresh(
+        &self,
+        client_api_url: &str,
+        transport: &dyn Transport
+    ) -> Result<(), Error> {
+        let refresh_token = self.tokens.read().unwrap().refresh_token.clone()
+            .ok_or_else(|| Error::SessionExpired { status: None, error: "No refresh token".into() })?;
+
+        let res = transport.send(HttpRequest {
+            method: Method::POST,
+            url: format!("{}/auth/session", client_api_url),
+            headers: bearer_headers(&refresh_token)?,
+            body: None,
+        }).await?;
+
+        if !res.status.is_success(){
+            return Err(Error::from_response(&res).into_session_expired())
+        }
+
+        let res = serde_json::from_slice::<RefreshResponse>(&res.body).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+        self.tokens.write().unwrap().access_token = res.access_token;
+        self.persist()
+    }
+}
+
+impl From<Tokens> for Session {
+    fn from(tokens: Tokens) -> Self {
+        Session { tokens: Arc::new(RwLock::new(tokens)), storage: None }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Credentials used to log in against one of the App Services auth providers
+pub enum Credentials {
+    /// the `anon-user` provider
+    Anonymous,
+    /// the `local-userpass` provider
+    EmailPassword {
+        email: String,
+        password: String,
+    },
+    /// the `api-key` provider, using a server or user api key
+    ApiKey(String),
+    /// the `custom-token` provider, using a custom JWT
+    Jwt(String),
+}
+
+impl Credentials {
+    /// credentials for the anonymous provider
+    pub fn anonymous() -> Self {
+        Credentials::Anonymous
+    }
+    /// credentials for the email/password provider
+    pub fn email_password(email: impl Into<String>, password: impl Into<String>) -> Self {
+        Credentials::EmailPassword { email: email.into(), password: password.into() }
+    }
+    /// credentials for the api key provider
+    pub fn api_key(key: impl Into<String>) -> Self {
+        Credentials::ApiKey(key.into())
+    }
+    /// credentials for the custom jwt provider
+    pub fn jwt(token: impl Into<String>) -> Self {
+        Credentials::Jwt(token.into())
+    }
+
+    /// the name of the auth provider, which is inserted into the login url
+    pub fn provider(&self) -> &'static str {
+        match self {
+            Credentials::Anonymous => "anon-user",
+            Credentials::EmailPassword { .. } => "local-userpass",
+            Credentials::ApiKey(_) => "api-key",
+            Credentials::Jwt(_) => "custom-token",
+        }
+    }
+
+    /// the body of the login request
+    pub(crate) fn payload(&self) -> LoginRequest {
+        match self {
+            Credentials::Anonymous => LoginRequest::default(),
+            Credentials::EmailPassword { email, password } => LoginRequest {
+                username: Some(email.clone()),
+                password: Some(password.clone()),
+                ..Default::default()
+            },
+            Credentials::ApiKey(key) => LoginRequest {
+                key: Some(key.clone()),
+                ..Default::default()
+            },
+            Credentials::Jwt(token) => LoginRequest {
+                token: Some(token.clone()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct LoginRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<String>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The tokens of a logged in user
+pub struct Tokens {
+    /// short lived token, which is sent as bearer token
+    pub access_token: String,
+    /// long lived token, which is used to create new access tokens
+    pub refresh_token: Option<String>,
+    pub user_id: Option<String>,
+    pub device_id: Option<String>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Deserialize)]
+struct RefreshResponse {
+    access_token: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, atomic::{AtomicU32, Ordering}};
+
+    use http::{HeaderMap, Method, StatusCode};
+
+    use super::{App, Credentials};
+    use crate::{Collection, Error, HttpRequest, HttpResponse, MemoryStorage, RetryPolicy, Session, SessionStorage, Transport, transport::BoxFuture};
+    use crate::scripted::{Scripted, header, json_body};
+
+    #[derive(Debug, Default)]
+    /// logs in a new user on every login request and accepts every logout
+    struct Server {
+        logins: AtomicU32,
+    }
+
+    impl Transport for Server {
+        fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+            let (status, body) = match (request.method, request.url.rsplit('/').next()) {
+                (Method::POST, Some("login")) => {
+                    let n = self.logins.fetch_add(1, Ordering::SeqCst);
+                    (StatusCode::OK, format!(r#"{{"access_token":"a{n}","refresh_token":"r{n}","user_id":"u{n}","device_id":"d"}}"#))
+                },
+                (Method::DELETE, Some("session")) => (StatusCode::NO_CONTENT, String::new()),
+                _ => (StatusCode::NOT_FOUND, String::new()),
+            };
+            Box::pin(async move { Ok(HttpResponse { status, headers: HeaderMap::new(), body: body.into_bytes() }) })
+        }
+    }
+
+    fn new_app(storage: Arc<MemoryStorage>) -> App {
+        App::new()
+            .application_id("app")
+            .transport(Arc::new(Server::default()) as Arc<dyn Transport>)
+            .storage(Some(storage as Arc<dyn SessionStorage>))
+            .build()
+    }
+
+    #[test]
+    fn logged_out_users_are_not_current() {
+        let storage = Arc::new(MemoryStorage::new());
+        let app = new_app(storage.clone());
+        futures_executor::block_on(async {
+            app.log_in(Credentials::anonymous()).await.unwrap();
+            let second = app.log_in(Credentials::anonymous()).await.unwrap();
+            assert_eq!(app.current_user().unwrap().id(), "u1");
+
+            second.log_out().await.unwrap();
+            assert_eq!(app.current_user().unwrap().id(), "u0");
+            assert_eq!(app.all_users().iter().map(|x| x.id().to_string()).collect::<Vec<_>>(), vec!["u0", "u1"]);
+            assert!(matches!(app.switch_user("u1"), Err(Error::Auth { .. })));
+
+            app.current_user().unwrap().log_out().await.unwrap();
+            assert!(app.current_user().is_none());
+        });
+
+        // a restored app skips the logged out users as well
+        let restored = new_app(storage);
+        assert!(restored.restore().unwrap().is_none());
+        assert_eq!(restored.all_users().len(), 2);
+    }
+
+    fn collection() -> Collection {
+        Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "c".into() }
+    }
+
+    #[test]
+    fn expired_access_tokens_are_refreshed_and_the_request_replayed() {
+        let transport = Scripted::new();
+        transport
+            .respond(401, r#"{"error":"invalid session: access token expired","error_code":"InvalidSession"}"#)
+            .respond(201, r#"{"access_token":"new"}"#)
+            .respond(200, r#"{"document":null}"#);
+        let session = Session::new("old", Some("refresh".into()));
+        let client = transport.client(session.clone());
+        futures_executor::block_on(client.find_one(collection(), None, None)).unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(header(&requests[0], "authorization"), Some("Bearer old"));
+        assert_eq!((&requests[1].method, requests[1].url.as_str()), (&Method::POST, "https://realm.mongodb.com/api/client/v2.0/auth/session"));
+        assert_eq!(header(&requests[1], "authorization"), Some("Bearer refresh"));
+        assert_eq!(header(&requests[2], "authorization"), Some("Bearer new"));
+        assert_eq!(requests[2].url, requests[0].url);
+        assert_eq!(session.access_token(), "new");
+    }
+
+    #[test]
+    fn unavailable_refresh_is_retried() {
+        let transport = Scripted::new();
+        transport
+            .respond(401, "")
+            .respond(503, r#"{"error":"unavailable"}"#)
+            .respond(401, "")
+            .respond(201, r#"{"access_token":"new"}"#)
+            .respond(200, r#"{"document":null}"#);
+        let mut client = transport.client(Session::new("old", Some("refresh".into())));
+        client.retry_policy = RetryPolicy::default();
+        futures_executor::block_on(client.find_one(collection(), None, None)).unwrap();
+        assert_eq!(transport.requests().len(), 5);
+        assert_eq!(transport.sleeps().len(), 1);
+    }
+
+    #[test]
+    fn unavailable_refresh_keeps_the_session() {
+        let transport = Scripted::new();
+        transport.respond(401, "").respond(503, r#"{"error":"unavailable"}"#);
+        let session = Session::new("old", Some("refresh".into()));
+        let mut client = transport.client(session.clone());
+        client.retry_policy = RetryPolicy::none();
+        let res = futures_executor::block_on(client.find_one(collection(), None, None));
+
+        assert!(matches!(&res, Err(x @ Error::Api { status: StatusCode::SERVICE_UNAVAILABLE, .. }) if x.is_transient()), "{:?}", res);
+        assert!(session.can_refresh());
+    }
+
+    #[test]
+    fn rejected_refresh_tokens_expire_the_session() {
+        let transport = Scripted::new();
+        transport.respond(401, "").respond(401, r#"{"error":"invalid session"}"#);
+        let client = transport.client(Session::new("old", Some("refresh".into())));
+        let res = futures_executor::block_on(client.find_one(collection(), None, None));
+        assert!(matches!(res, Err(Error::SessionExpired { status: Some(StatusCode::UNAUTHORIZED), .. })), "{:?}", res);
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    fn tokens(n: u32) -> String {
+        format!(r#"{{"access_token":"a{n}","refresh_token":"r{n}","user_id":"u{n}","device_id":"d"}}"#)
+    }
+
+    fn user_ids(app: &App) -> Vec<String> {
+        app.all_users().iter().map(|x| x.id().to_string()).collect()
+    }
+
+    #[test]
+    fn log_in_with_every_credential_type() {
+        let transport = Scripted::new();
+        let app = App::new().application_id("app").transport(transport.clone() as Arc<dyn Transport>).build();
+        for (n, (credentials, provider, body)) in [
+            (Credentials::anonymous(), "anon-user", serde_json::json!({})),
+            (Credentials::email_password("ada@example.com", "secret"), "local-userpass", serde_json::json!({"username": "ada@example.com", "password": "secret"})),
+            (Credentials::api_key("key"), "api-key", serde_json::json!({"key": "key"})),
+            (Credentials::jwt("jwt"), "custom-token", serde_json::json!({"token": "jwt"})),
+        ].into_iter().enumerate() {
+            transport.respond(200, &tokens(n as u32));
+            let user = futures_executor::block_on(app.log_in(credentials)).unwrap();
+            assert_eq!((user.id(), user.access_token().as_str()), (format!("u{}", n).as_str(), format!("a{}", n).as_str()));
+
+            let request = transport.requests().pop().unwrap();
+            assert_eq!(request.method, Method::POST);
+            assert_eq!(request.url, format!("https://realm.mongodb.com/api/client/v2.0/app/app/auth/providers/{}/login", provider));
+            assert_eq!(header(&request, "content-type"), Some("application/json"));
+            assert_eq!(header(&request, "authorization"), None);
+            assert_eq!(json_body(&request), body);
+        }
+        assert_eq!(user_ids(&app), ["u3", "u2", "u1", "u0"]);
+    }
+
+    #[test]
+    fn rejected_log_in_adds_no_user() {
+        let transport = Scripted::new();
+        transport.respond(401, r#"{"error":"invalid username/password","error_code":"InvalidPassword"}"#);
+        let app = App::new().application_id("app").transport(transport.clone() as Arc<dyn Transport>).build();
+        let res = futures_executor::block_on(app.log_in(Credentials::email_password("ada@example.com", "wrong")));
+        assert!(matches!(&res, Err(x @ Error::Auth { .. }) if x.error_code() == Some("InvalidPassword")), "{:?}", res);
+        assert!(app.current_user().is_none());
+    }
+
+    #[test]
+    fn current_switch_and_remove_keep_the_order() {
+        let storage = Arc::new(MemoryStorage::new());
+        let transport = Scripted::new();
+        let app = App::new()
+            .application_id("app")
+            .transport(transport.clone() as Arc<dyn Transport>)
+            .storage(Some(storage.clone() as Arc<dyn SessionStorage>))
+            .build();
+        futures_executor::block_on(async {
+            for n in 0..3 {
+                transport.respond(200, &tokens(n));
+                app.log_in(Credentials::anonymous()).await.unwrap();
+            }
+            assert_eq!(user_ids(&app), ["u2", "u1", "u0"]);
+            assert_eq!(app.current_user().unwrap().id(), "u2");
+
+            assert_eq!(app.switch_user("u0").unwrap().id(), "u0");
+            assert_eq!(user_ids(&app), ["u0", "u2", "u1"]);
+            assert!(app.current_user().unwrap().is_current());
+            assert_eq!(storage.user_ids("app").unwrap(), ["u0", "u2", "u1"]);
+            assert!(matches!(app.switch_user("u9"), Err(Error::Auth { .. })));
+
+            // logging in again moves the user to the front without adding it twice
+            transport.respond(200, &tokens(1));
+            app.log_in(Credentials::anonymous()).await.unwrap();
+            assert_eq!(user_ids(&app), ["u1", "u0", "u2"]);
+
+            transport.respond(204, "");
+            app.remove_user("u1").await.unwrap();
+            let request = transport.requests().pop().unwrap();
+            assert_eq!((&request.method, request.url.as_str()), (&Method::DELETE, "https://realm.mongodb.com/api/client/v2.0/auth/session"));
+            assert_eq!(header(&request, "authorization"), Some("Bearer r1"));
+            assert_eq!(user_ids(&app), ["u0", "u2"]);
+            assert_eq!(app.current_user().unwrap().id(), "u0");
+            assert!(storage.get("app", "u1").unwrap().is_none());
+            assert_eq!(storage.user_ids("app").unwrap(), ["u0", "u2"]);
+        });
+    }
+
+    #[test]
+    fn restore_from_the_storage() {
+        let storage = Arc::new(MemoryStorage::new());
+        let transport = Scripted::new();
+        let app = || App::new()
+            .application_id("app")
+            .transport(transport.clone() as Arc<dyn Transport>)
+            .storage(Some(storage.clone() as Arc<dyn SessionStorage>))
+            .build();
+
+        let first = app();
+        assert!(first.restore().unwrap().is_none());
+        futures_executor::block_on(async {
+            for n in 0..2 {
+                transport.respond(200, &tokens(n));
+                first.log_in(Credentials::anonymous()).await.unwrap();
+            }
+        });
+        first.switch_user("u0").unwrap();
+
+        let restored = app();
+        let session = restored.current_session().unwrap().unwrap();
+        assert_eq!((session.user_id().as_deref(), session.access_token().as_str()), (Some("u0"), "a0"));
+        assert_eq!(user_ids(&restored), ["u0", "u1"]);
+
+        transport.respond(200, r#"{"document":null}"#);
+        let client = restored.restore().unwrap().unwrap();
+        futures_executor::block_on(client.find_one(collection(), None, None)).unwrap();
+        assert_eq!(header(&transport.requests().pop().unwrap(), "authorization"), Some("Bearer a0"));
+    }
+}
@@ -0,0 +1,178 @@
+use bson::Bson;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How requests and responses are encoded on the wire
+pub enum WireFormat {
+    /// plain json (`application/json`)
+    ///
+    /// The server answers with plain json, so `ObjectId`, `DateTime`, `Decimal128`, `Int64`, binary etc. lose their type.
+    Json,
+    /// [canonical extended json](https://www.mongodb.com/docs/manual/reference/mongodb-extended-json/) (`application/ejson`), which preserves all bson types
+    CanonicalEjson,
+    /// [relaxed extended json](https://www.mongodb.com/docs/manual/reference/mongodb-extended-json/) (`application/ejson`),
+    /// which writes numbers and dates in their natural json representation
+    ///
+    /// Integers don't keep their width: an `Int64`, which fits into 32 bits, is read back as `Int32`.
+    RelaxedEjson,
+}
+
+impl WireFormat {
+    /// the value of the `Content-Type` and `Accept` headers
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            WireFormat::Json => "application/json",
+            WireFormat::CanonicalEjson | WireFormat::RelaxedEjson => "application/ejson",
+        }
+    }
+
+    /// serializes the value into a request body
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<String, Error> {
+        let value = match self {
+            WireFormat::Json => return serde_json::to_string(value).map_err(|x| Error::Serialization(format!("{:?}", x))),
+            WireFormat::CanonicalEjson => to_bson(value)?.into_canonical_extjson(),
+            WireFormat::RelaxedEjson => to_bson(value)?.into_relaxed_extjson(),
+        };
+        serde_json::to_string(&value).map_err(|x| Error::Serialization(format!("{:?}", x)))
+    }
+
+    /// deserializes a response body
+    ///
+    /// Extended json is accepted in canonical as well as in relaxed mode.
+    pub fn decode<T: DeserializeOwned>(&self, body: &str) -> Result<T, Error> {
+        match self {
+            WireFormat::Json => serde_json::from_str(body).map_err(|x| Error::Deserialization(format!("{:?}", x))),
+            WireFormat::CanonicalEjson | WireFormat::RelaxedEjson => {
+                let value: serde_json::Value = serde_json::from_str(body).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+                from_bson(from_extjson(value)?)
+            }
+        }
+    }
+}
+
+/// parses a canonical or relaxed extended json value
+pub fn from_extjson(value: serde_json::Value) -> Result<Bson, Error> {
+    Bson::try_from(value).map_err(|x| Error::Deserialization(format!("Invalid extended json: {:?}", x)))
+}
+
+fn to_bson<T: Serialize>(value: &T) -> Result<Bson, Error> {
+    bson::to_bson(value).map_err(|x| Error::Serialization(format!("{:?}", x)))
+}
+
+fn from_bson<T: DeserializeOwned>(value: Bson) -> Result<T, Error> {
+    bson::from_bson(value).map_err(|x| Error::Deserialization(format!("{:?}", x)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, sync::{Arc, Mutex}};
+
+    use bson::{Binary, Bson, DateTime, Decimal128, Document, Regex, Timestamp, doc, oid::ObjectId, spec::BinarySubtype};
+    use http::{HeaderMap, StatusCode};
+
+    use super::WireFormat;
+    use crate::{Authentication, Client, Collection, Error, HttpRequest, HttpResponse, Transport, transport::BoxFuture};
+
+    /// a document with a value of every bson type
+    fn all_types() -> Document {
+        doc! {
+            "objectId": ObjectId::from_str("64b7f1c2a1b2c3d4e5f60718").unwrap(),
+            "dateTime": DateTime::from_millis(1_689_712_345_678),
+            "decimal128": Bson::Decimal128(Decimal128::from_bytes([1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x3e, 0x30])),
+            "int32": 42_i32,
+            "int64": 9_007_199_254_740_993_i64,
+            "double": 1.5_f64,
+            "binary": Binary { subtype: BinarySubtype::Generic, bytes: vec![0, 1, 2, 255] },
+            "uuid": Binary { subtype: BinarySubtype::Uuid, bytes: vec![7; 16] },
+            "regex": Regex { pattern: "^a.*z$".into(), options: "i".into() },
+            "timestamp": Timestamp { time: 1_689_712_345, increment: 7 },
+            "minKey": Bson::MinKey,
+            "maxKey": Bson::MaxKey,
+            "null": Bson::Null,
+            "string": "text",
+            "boolean": true,
+            "array": [1_i32, "two", { "three": 3_i64 << 40 }, [Bson::Null, Bson::MinKey]],
+            "document": { "nested": { "objectId": ObjectId::from_str("64b7f1c2a1b2c3d4e5f60719").unwrap(), "dateTime": DateTime::from_millis(0) } },
+        }
+    }
+
+    fn round_trip(format: WireFormat, document: &Document) -> Document {
+        let body = format.encode(document).unwrap();
+        format.decode(&body).unwrap()
+    }
+
+    #[test]
+    fn canonical_ejson_preserves_every_type() {
+        let document = all_types();
+        assert_eq!(round_trip(WireFormat::CanonicalEjson, &document), document);
+    }
+
+    #[test]
+    fn relaxed_ejson_preserves_every_type() {
+        let document = all_types();
+        assert_eq!(round_trip(WireFormat::RelaxedEjson, &document), document);
+    }
+
+    #[test]
+    fn relaxed_ejson_narrows_small_int64() {
+        let document = round_trip(WireFormat::RelaxedEjson, &doc! { "small": 5_i64, "double": 2.0_f64 });
+        assert_eq!(document.get("small"), Some(&Bson::Int32(5)));
+        assert_eq!(document.get("double"), Some(&Bson::Double(2.0)));
+    }
+
+    #[test]
+    fn canonical_ejson_writes_type_wrappers() {
+        let body = WireFormat::CanonicalEjson.encode(&doc! { "int32": 1_i32, "int64": 1_i64, "double": 1.0_f64 }).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value, serde_json::json!({
+            "int32": { "$numberInt": "1" },
+            "int64": { "$numberLong": "1" },
+            "double": { "$numberDouble": "1.0" },
+        }));
+    }
+
+    #[test]
+    fn decode_accepts_both_ejson_modes() {
+        let canonical = WireFormat::CanonicalEjson.encode(&all_types()).unwrap();
+        let relaxed = WireFormat::RelaxedEjson.encode(&all_types()).unwrap();
+        assert_eq!(WireFormat::CanonicalEjson.decode::<Document>(&relaxed).unwrap(), all_types());
+        assert_eq!(WireFormat::RelaxedEjson.decode::<Document>(&canonical).unwrap(), all_types());
+    }
+
+    #[derive(Debug, Default)]
+    struct Recorder {
+        headers: Mutex<Vec<HeaderMap>>,
+    }
+
+    impl Transport for Recorder {
+        fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+            self.headers.lock().unwrap().push(request.headers);
+            Box::pin(async move { Ok(HttpResponse { status: StatusCode::OK, headers: HeaderMap::new(), body: br#"{"document":null}"#.to_vec() }) })
+        }
+    }
+
+    #[test]
+    fn send_action_sets_the_content_type_of_the_format() {
+        for (format, content_type) in [
+            (WireFormat::Json, "application/json"),
+            (WireFormat::CanonicalEjson, "application/ejson"),
+            (WireFormat::RelaxedEjson, "application/ejson"),
+        ] {
+            let transport = Arc::new(Recorder::default());
+            let client = Client::new()
+                .application_id("app")
+                .authentication(Authentication::ApiKey("key".into()))
+                .wire_format(format)
+                .transport(transport.clone() as Arc<dyn Transport>)
+                .build();
+            let collection = Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "c".into() };
+            futures_executor::block_on(client.find_one(collection, None, None)).unwrap();
+
+            let headers = transport.headers.lock().unwrap().pop().unwrap();
+            assert_eq!(headers.get("content-type").unwrap(), content_type);
+            assert_eq!(headers.get("accept").unwrap(), content_type);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use bson::Bson;
+use http::{Method, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::Serialize;
+
+use crate::{App, Error, HttpRequest, ejson};
+
+#[derive(Debug, Clone, Copy)]
+/// The flows of the email/password (`local-userpass`) auth provider, which don't require a logged in user, see [App::email_password_auth]
+///
+/// Users are logged in with [Credentials::email_password](crate::Credentials::email_password).
+pub struct EmailPasswordAuth<'a> {
+    app: &'a App,
+}
+
+impl App {
+    /// the flows of the email/password auth provider, like registering users
+    pub fn email_password_auth(&self) -> EmailPasswordAuth<'_> {
+        EmailPasswordAuth { app: self }
+    }
+}
+
+#[allow(unused)]
+impl<'a> EmailPasswordAuth<'a> {
+    /// # Register a User
+    ///
+    /// Registers a new user, who has to be confirmed before logging in, depending on the provider settings
+    pub async fn register_user(&self, email: &str, password: &str) -> Result<(), Error> {
+        self.post("register", &EmailPasswordRequest { email: Some(email), password: Some(password), ..Default::default() }).await
+    }
+    /// # Confirm a User
+    ///
+    /// confirms the user with the `token` and `tokenId` query parameters of the confirmation link
+    pub async fn confirm_user(&self, token: &str, token_id: &str) -> Result<(), Error> {
+        self.post("confirm", &EmailPasswordRequest { token: Some(token), token_id: Some(token_id), ..Default::default() }).await
+    }
+    /// # Resend the Confirmation Email
+    pub async fn resend_confirmation_email(&self, email: &str) -> Result<(), Error> {
+        self.post("confirm/send", &EmailPasswordRequest { email: Some(email), ..Default::default() }).await
+    }
+    /// # Retry the Custom Confirmation
+    ///
+    /// calls the custom confirmation function of the provider again
+    pub async fn retry_custom_confirmation(&self, email: &str) -> Result<(), Error> {
+        self.post("confirm/call", &EmailPasswordRequest { email: Some(email), ..Default::default() }).await
+    }
+    /// # Send the Reset Password Email
+    pub async fn send_reset_password_email(&self, email: &str) -> Result<(), Error> {
+        self.post("reset/send", &EmailPasswordRequest { email: Some(email), ..Default::default() }).await
+    }
+    /// # Reset the Password
+    ///
+    /// sets the new password with the `token` and `tokenId` query parameters of the reset link
+    pub async fn reset_password(&self, token: &str, token_id: &str, password: &str) -> Result<(), Error> {
+        self.post("reset", &EmailPasswordRequest { token: Some(token), token_id: Some(token_id), password: Some(password), ..Default::default() }).await
+    }
+    /// # Call the Reset Password Function
+    ///
+    /// Resets the password using the custom reset function of the provider.
+    /// The arguments are passed to the function after the email and password, encoded as canonical extended json.
+    pub async fn call_reset_password_function(&self, email: &str, password: &str, args: Vec<Bson>) -> Result<(), Error> {
+        self.post("reset/call", &EmailPasswordRequest { email: Some(email), password: Some(password), arguments: Some(args), ..Default::default() }).await
+    }
+
+    /// posts the body to the route of the provider
+    async fn post(&self, route: &str, req: &EmailPasswordRequest<'_>) -> Result<(), Error> {
+        self.app.resolve_location().await?;
+        let mut header_map = HeaderMap::new();
+        header_map.append(HeaderName::from_static("content-type"), HeaderValue::from_static("application/json"));
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("application/json"));
+
+        let res = self.app.transport.send(HttpRequest {
+            method: Method::POST,
+            url: format!("{}/auth/providers/local-userpass/{}", self.app.get_url(), route),
+            headers: header_map,
+            body: Some(ejson::WireFormat::CanonicalEjson.encode(req)?.into_bytes()),
+        }).await?;
+
+        if !res.status.is_success(){
+            return Err(Error::from_response(&res))
+        }
+        Ok(())
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmailPasswordRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    arguments: Option<Vec<Bson>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bson::Bson;
+    use futures_executor::block_on;
+    use http::Method;
+    use serde_json::json;
+
+    use crate::{App, Error, Transport};
+    use crate::scripted::{Scripted, header, json_body};
+
+    #[test]
+    fn every_flow_posts_to_its_route() {
+        let transport = Scripted::new();
+        let app = App::new().application_id("app").transport(transport.clone() as Arc<dyn Transport>).build();
+        let auth = app.email_password_auth();
+        for _ in 0..7 {
+            transport.respond(200, "{}");
+        }
+        block_on(async {
+            auth.register_user("ada@example.com", "secret").await.unwrap();
+            auth.confirm_user("token", "id").await.unwrap();
+            auth.resend_confirmation_email("ada@example.com").await.unwrap();
+            auth.retry_custom_confirmation("ada@example.com").await.unwrap();
+            auth.send_reset_password_email("ada@example.com").await.unwrap();
+            auth.reset_password("token", "id", "new").await.unwrap();
+            auth.call_reset_password_function("ada@example.com", "new", vec![Bson::Int32(1), "a".into()]).await.unwrap();
+        });
+
+        let requests = transport.requests();
+        let expected = [
+            ("register", json!({"email": "ada@example.com", "password": "secret"})),
+            ("confirm", json!({"token": "token", "tokenId": "id"})),
+            ("confirm/send", json!({"email": "ada@example.com"})),
+            ("confirm/call", json!({"email": "ada@example.com"})),
+            ("reset/send", json!({"email": "ada@example.com"})),
+            ("reset", json!({"password": "new", "token": "token", "tokenId": "id"})),
+            ("reset/call", json!({"email": "ada@example.com", "password": "new", "arguments": [{"$numberInt": "1"}, "a"]})),
+        ];
+        assert_eq!(requests.len(), expected.len());
+        for (request, (route, body)) in requests.iter().zip(expected) {
+            assert_eq!(request.method, Method::POST);
+            assert_eq!(request.url, format!("https://realm.mongodb.com/api/client/v2.0/app/app/auth/providers/local-userpass/{}", route));
+            assert_eq!(header(request, "content-type"), Some("application/json"));
+            assert_eq!(header(request, "authorization"), None);
+            assert_eq!(json_body(request), body, "{}", route);
+        }
+    }
+
+    #[test]
+    fn failures_are_returned() {
+        let transport = Scripted::new();
+        transport.respond(409, r#"{"error":"name already in use","error_code":"AccountNameInUse"}"#);
+        let app = App::new().application_id("app").transport(transport.clone() as Arc<dyn Transport>).build();
+        let res = block_on(app.email_password_auth().register_user("ada@example.com", "secret"));
+        assert!(matches!(&res, Err(x @ Error::Api { .. }) if x.error_code() == Some("AccountNameInUse")), "{:?}", res);
+    }
+}
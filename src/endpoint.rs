@@ -0,0 +1,250 @@
+use http::{Method, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Client, Error, HttpRequest, HttpResponse, WireFormat, transport::encode_query};
+
+#[derive(Debug)]
+/// Builder of a request to a custom [HTTPS endpoint](https://www.mongodb.com/docs/atlas/app-services/data-api/custom-endpoints/) of the app, see [Client::custom_endpoint]
+pub struct CustomEndpoint<'a> {
+    client: &'a Client,
+    route: String,
+    method: Method,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Option<Result<String, Error>>,
+    body_format: WireFormat,
+    response_format: WireFormat,
+}
+
+#[allow(unused)]
+impl Client {
+    /// gets the url of a custom endpoint https://data.mongodb-api.com/app/<App ID>/endpoint/<Route>
+    pub(crate) fn get_endpoint_url(&self, route: &str) -> Result<String, Error> {
+        Ok(format!(
+            "{}/app/{}/endpoint/{}",
+            self.location.data_api_hostname(&self.base_url, &self.deployment_region)?,
+            self.application_id,
+            route.trim_start_matches('/')
+        ))
+    }
+
+    /// # Call a Custom HTTPS Endpoint
+    ///
+    /// Builds a `GET` request to the endpoint at `/endpoint/<route>`, which is authenticated like the data api actions.
+    /// The response is decoded like the documents of the client, see [Client::wire_format].
+    pub fn custom_endpoint(&self, route: impl Into<String>) -> CustomEndpoint<'_> {
+        CustomEndpoint {
+            client: self,
+            route: route.into(),
+            method: Method::GET,
+            query: Vec::new(),
+            headers: Vec::new(),
+            body: None,
+            body_format: self.wire_format,
+            response_format: self.wire_format,
+        }
+    }
+}
+
+#[allow(unused)]
+impl<'a> CustomEndpoint<'a> {
+    /// the http method of the endpoint, `GET` by default
+    pub fn method(mut self, method: Method) -> Self {
+        self.method = method;
+        self
+    }
+    /// adds a query parameter, it gets percent encoded
+    pub fn query(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.query.push((name.into(), value.into()));
+        self
+    }
+    /// adds a header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+    /// sends the secret of the endpoint as the `secret` query parameter
+    pub fn secret(self, secret: impl Into<String>) -> Self {
+        self.query("secret", secret)
+    }
+    /// sends the secret of the endpoint in the given header, e.g. for endpoints which verify it in a function
+    pub fn secret_header(self, name: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.header(name, secret)
+    }
+    /// the body, encoded as json
+    pub fn json<T: Serialize>(self, body: &T) -> Self {
+        self.body(WireFormat::Json, body)
+    }
+    /// the body, encoded as canonical extended json, so all bson types are preserved
+    pub fn ejson<T: Serialize>(self, body: &T) -> Self {
+        self.body(WireFormat::CanonicalEjson, body)
+    }
+    /// the body, encoded in the given format
+    pub fn body<T: Serialize>(mut self, format: WireFormat, body: &T) -> Self {
+        self.body = Some(format.encode(body));
+        self.body_format = format;
+        self
+    }
+    /// how the response is decoded by [CustomEndpoint::send_as], the format of the client by default
+    ///
+    /// Endpoints, which respond with the result of their function, return json or extended json depending on it.
+    pub fn response_format(mut self, format: WireFormat) -> Self {
+        self.response_format = format;
+        self
+    }
+
+    /// sends the request and returns the response, fails if the status code isn't successful
+    ///
+    /// Requests with the idempotent methods `GET`, `HEAD`, `PUT`, `DELETE` and `OPTIONS` are retried according to the [RetryPolicy](crate::RetryPolicy),
+    /// others like `POST` and `PATCH` only if it allows retrying non-idempotent requests.
+    pub async fn send(self) -> Result<HttpResponse, Error> {
+        let body = self.body.transpose()?;
+        self.client.resolve_location().await?;
+
+        let mut url = self.client.get_endpoint_url(&self.route)?;
+        for (i, (name, value)) in self.query.iter().enumerate() {
+            url.push(if i == 0 { '?' } else { '&' });
+            url.push_str(&encode_query(name));
+            url.push('=');
+            url.push_str(&encode_query(value));
+        }
+
+        let mut header_map = HeaderMap::new();
+        if body.is_some() {
+            header_map.append(HeaderName::from_static("content-type"), HeaderValue::from_static(self.body_format.content_type()));
+        }
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static(self.response_format.content_type()));
+        for (name, value) in &self.headers {
+            header_map.append(
+                HeaderName::from_bytes(name.as_bytes()).map_err(|x| Error::Serialization(format!("{:?}", x)))?,
+                HeaderValue::from_str(value).map_err(|x| Error::Serialization(format!("{:?}", x)))?,
+            );
+        }
+
+        let idempotent = matches!(self.method, Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS);
+        self.client.send_authorized(HttpRequest {
+            method: self.method,
+            url,
+            headers: header_map,
+            body: body.map(|x| x.into_bytes()),
+        }, idempotent).await
+    }
+    /// sends the request and decodes the response into `T`
+    pub async fn send_as<T: DeserializeOwned>(self) -> Result<T, Error> {
+        let format = self.response_format;
+        let res = self.send().await?;
+        format.decode(&res.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bson::{Bson, Document, doc};
+    use futures_executor::block_on;
+    use http::Method;
+    use serde_json::json;
+
+    use crate::{Client, Error, RetryPolicy, Session, WireFormat};
+    use crate::scripted::{Scripted, header, json_body};
+
+    fn client(transport: &Arc<Scripted>) -> Client {
+        let mut client = transport.client(Session::new("access", None));
+        client.retry_policy = RetryPolicy::new().jitter(false).build();
+        client
+    }
+
+    #[test]
+    fn query_parameters_are_encoded() {
+        let transport = Scripted::new();
+        transport.respond(200, "{}");
+        block_on(client(&transport).custom_endpoint("/search")
+            .query("q", "a b&c=d/ü")
+            .query("page", "2")
+            .secret("s3cr~t")
+            .send()).unwrap();
+        let request = &transport.requests()[0];
+        assert_eq!(request.method, Method::GET);
+        assert_eq!(request.url, "https://data.mongodb-api.com/app/app/endpoint/search?q=a%20b%26c%3Dd%2F%C3%BC&page=2&secret=s3cr~t");
+        assert_eq!(header(request, "authorization"), Some("Bearer access"));
+        assert_eq!(header(request, "content-type"), None);
+        assert!(request.body.is_none());
+    }
+
+    #[test]
+    fn secret_header() {
+        let transport = Scripted::new();
+        transport.respond(200, "{}");
+        block_on(client(&transport).custom_endpoint("hook").secret_header("x-hook-secret", "s").header("x-other", "o").send()).unwrap();
+        let request = &transport.requests()[0];
+        assert_eq!(request.url, "https://data.mongodb-api.com/app/app/endpoint/hook");
+        assert_eq!(header(request, "x-hook-secret"), Some("s"));
+        assert_eq!(header(request, "x-other"), Some("o"));
+
+        let res = block_on(client(&transport).custom_endpoint("hook").secret_header("bad header", "s").send());
+        assert!(matches!(res, Err(Error::Serialization(..))), "{:?}", res);
+        assert_eq!(transport.requests().len(), 1);
+    }
+
+    #[test]
+    fn json_and_ejson_bodies() {
+        let transport = Scripted::new();
+        transport.respond(200, "{}").respond(200, "{}");
+        let client = client(&transport);
+        block_on(client.custom_endpoint("items").method(Method::POST).json(&doc! { "n": 5_i64 }).send()).unwrap();
+        block_on(client.custom_endpoint("items").method(Method::POST).ejson(&doc! { "n": 5_i64 }).send()).unwrap();
+
+        let requests = transport.requests();
+        assert_eq!(header(&requests[0], "content-type"), Some("application/json"));
+        assert_eq!(json_body(&requests[0]), json!({"n": 5}));
+        assert_eq!(header(&requests[1], "content-type"), Some("application/ejson"));
+        assert_eq!(json_body(&requests[1]), json!({"n": {"$numberLong": "5"}}));
+    }
+
+    #[test]
+    fn response_formats() {
+        let body = r#"{"n":{"$numberLong":"5"}}"#;
+        let transport = Scripted::new();
+        transport.respond(200, body).respond(200, body).respond(200, body);
+        let mut client = client(&transport);
+        client.wire_format = WireFormat::Json;
+
+        let res = block_on(client.custom_endpoint("n").send_as::<serde_json::Value>()).unwrap();
+        assert_eq!(res, json!({"n": {"$numberLong": "5"}}));
+        assert_eq!(header(&transport.requests()[0], "accept"), Some("application/json"));
+
+        let res = block_on(client.custom_endpoint("n").response_format(WireFormat::CanonicalEjson).send_as::<Document>()).unwrap();
+        assert_eq!(res.get("n"), Some(&Bson::Int64(5)));
+        assert_eq!(header(&transport.requests()[1], "accept"), Some("application/ejson"));
+
+        let res = block_on(client.custom_endpoint("n").send()).unwrap();
+        assert_eq!(res.text(), body);
+    }
+
+    #[test]
+    fn only_idempotent_methods_are_retried() {
+        for (method, attempts) in [
+            (Method::GET, 2),
+            (Method::HEAD, 2),
+            (Method::PUT, 2),
+            (Method::DELETE, 2),
+            (Method::OPTIONS, 2),
+            (Method::POST, 1),
+            (Method::PATCH, 1),
+        ] {
+            let transport = Scripted::new();
+            transport.respond(503, "").respond(200, "{}");
+            let res = block_on(client(&transport).custom_endpoint("items").method(method.clone()).send());
+            assert_eq!(res.is_ok(), attempts == 2, "{}", method);
+            assert_eq!(transport.requests().len(), attempts, "{}", method);
+        }
+
+        let transport = Scripted::new();
+        transport.respond(503, "").respond(200, "{}");
+        let mut client = client(&transport);
+        client.retry_policy = RetryPolicy::new().jitter(false).retry_non_idempotent(true).build();
+        block_on(client.custom_endpoint("items").method(Method::POST).json(&json!({})).send()).unwrap();
+        assert_eq!(transport.requests().len(), 2);
+    }
+}
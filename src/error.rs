@@ -0,0 +1,137 @@
+use std::fmt::Display;
+
+use http::StatusCode;
+use serde::Deserialize;
+
+use crate::{HttpResponse, graphql::GraphQlError, retry::TRANSIENT_STATUSES};
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+/// The errors of all api calls
+pub enum Error {
+    /// The request couldn't be sent or the response couldn't be read
+    Transport(String),
+    /// The request couldn't be serialized
+    Serialization(String),
+    /// The response couldn't be deserialized
+    Deserialization(String),
+    /// The server denied the request
+    Api {
+        status: StatusCode,
+        /// the error message, or the raw body if it couldn't be parsed
+        error: String,
+        /// e.g. `InvalidParameter` or `NoMatchingRuleFound`
+        error_code: Option<String>,
+        /// link to the server logs
+        link: Option<String>,
+    },
+    /// The credentials or tokens got rejected (status 401/403), or couldn't be sent
+    Auth {
+        status: Option<StatusCode>,
+        error: String,
+        error_code: Option<String>,
+    },
+    /// The access token expired and the session couldn't be refreshed, the user has to log in again
+    SessionExpired {
+        status: Option<StatusCode>,
+        error: String,
+    },
+    /// The GraphQL API answered with errors
+    GraphQl(Vec<GraphQlError>),
+    /// The [SessionStorage](crate::SessionStorage) couldn't be read or written
+    Storage(String),
+    /// The data api hostname couldn't be derived from the discovered [Location](crate::Location)
+    Location(String),
+    /// The server sent an `error` event on a change stream, see [Client::watch](crate::Client::watch)
+    ChangeStream {
+        error: String,
+        error_code: Option<String>,
+    },
+}
+
+impl Error {
+    /// parses the error body, which App Services returns with a non-success status code
+    pub fn from_response(res: &HttpResponse) -> Self {
+        let body = serde_json::from_slice::<ErrorBody>(&res.body).ok();
+        let (error, error_code, link) = match body {
+            Some(ErrorBody { error: Some(error), error_code, link }) => (error, error_code, link),
+            Some(ErrorBody { error: None, error_code, link }) => (res.text(), error_code, link),
+            None => (res.text(), None, None),
+        };
+        match res.status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Error::Auth { status: Some(res.status), error, error_code },
+            status => Error::Api { status, error, error_code, link },
+        }
+    }
+    /// turns a rejected refresh token (status 401/403 or the error code `InvalidSession`) into [Error::SessionExpired]
+    ///
+    /// Other failures like 429 or 503 stay [Error::Api], so they can be retried.
+    pub(crate) fn into_session_expired(self) -> Self {
+        match self {
+            Error::Auth { status, error, .. } => Error::SessionExpired { status, error },
+            Error::Api { status, error, error_code: Some(code), .. } if code == "InvalidSession" || code == "invalid_session" =>
+                Error::SessionExpired { status: Some(status), error },
+            x => x,
+        }
+    }
+
+    /// the status code of the response, if the server denied the request
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Api { status, .. } => Some(*status),
+            Error::Auth { status, .. } | Error::SessionExpired { status, .. } => *status,
+            _ => None,
+        }
+    }
+    /// the error code of the response, e.g. `InvalidParameter`
+    pub fn error_code(&self) -> Option<&str> {
+        match self {
+            Error::Api { error_code, .. } | Error::Auth { error_code, .. } | Error::ChangeStream { error_code, .. } => error_code.as_deref(),
+            _ => None,
+        }
+    }
+    /// true, if the access token expired and the session couldn't be refreshed
+    pub fn is_session_expired(&self) -> bool {
+        matches!(self, Error::SessionExpired { .. })
+    }
+    /// true, if the request got denied because of the authentication
+    pub fn is_auth(&self) -> bool {
+        matches!(self, Error::Auth { .. } | Error::SessionExpired { .. })
+    }
+    /// true, if sending the same request again may succeed:
+    /// transport errors and the [TRANSIENT_STATUSES](crate::retry::TRANSIENT_STATUSES), like the default [RetryPolicy](crate::RetryPolicy)
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Transport(_) => true,
+            Error::Api { status, .. } => TRANSIENT_STATUSES.contains(status),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Transport(x) => write!(f, "Transport error: {}", x),
+            Error::Serialization(x) => write!(f, "Format error: {}", x),
+            Error::Deserialization(x) => write!(f, "Failed to deserialize response: {}", x),
+            Error::Api { status, error, error_code, .. } => write!(f, "StatusCode: {}; {}; code: {}", status, error, error_code.as_deref().unwrap_or("-")),
+            Error::Auth { status, error, .. } => write!(f, "Authentication failed; StatusCode: {:?}; {}", status, error),
+            Error::SessionExpired { status, error } => write!(f, "Session expired; StatusCode: {:?}; {}", status, error),
+            Error::GraphQl(errors) => write!(f, "GraphQL errors: {}", errors.iter().map(|x| x.message.as_str()).collect::<Vec<_>>().join("; ")),
+            Error::Storage(x) => write!(f, "Session storage error: {}", x),
+            Error::Location(x) => write!(f, "Location error: {}", x),
+            Error::ChangeStream { error, error_code } => write!(f, "Change stream error: {}; code: {}", error, error_code.as_deref().unwrap_or("-")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Deserialize)]
+struct ErrorBody {
+    error: Option<String>,
+    error_code: Option<String>,
+    link: Option<String>,
+}
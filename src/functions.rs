@@ -0,0 +1,127 @@
+use bson::Bson;
+use http::{Method, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Client, Authentication, Error, HttpRequest, ejson};
+
+#[allow(unused)]
+impl Client {
+    /// gets the url of the function calls https://realm.mongodb.com/api/client/v2.0/app/<App ID>/functions/call
+    fn get_functions_url(&self) -> String {
+        format!("{}/app/{}/functions/call", self.client_api_url(), self.application_id)
+    }
+
+    /// # Call an App Services Function
+    ///
+    /// Calls the [function](https://www.mongodb.com/docs/atlas/app-services/functions/) with the given arguments as the logged in user
+    /// and returns its result. Arguments and result are encoded as canonical extended json, so all bson types are preserved.
+    ///
+    /// Functions can only be called with [Authentication::Bearer].
+    pub async fn call_function(&self, name: &str, args: Vec<Bson>) -> Result<Bson, Error> {
+        if let Authentication::ApiKey(_) = self.authentication {
+            return Err(Error::Auth { status: None, error: "Calling a function requires a logged in user".into(), error_code: None });
+        }
+
+        self.resolve_location().await?;
+        let req = FunctionCallRequest {
+            name: name.to_string(),
+            arguments: args,
+        };
+        let mut header_map = HeaderMap::new();
+        header_map.append(HeaderName::from_static("content-type"), HeaderValue::from_static("application/json"));
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("application/json"));
+
+        let res = self.send_authorized(HttpRequest {
+            method: Method::POST,
+            url: self.get_functions_url(),
+            headers: header_map,
+            body: Some(ejson::WireFormat::CanonicalEjson.encode(&req)?.into_bytes()),
+        }, false).await?;
+
+        let value: serde_json::Value = serde_json::from_slice(&res.body).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+        ejson::from_extjson(value)
+    }
+
+    /// # Call an App Services Function
+    ///
+    /// like [Client::call_function], but converts the result into `T`
+    pub async fn call_function_as<T: DeserializeOwned>(&self, name: &str, args: Vec<Bson>) -> Result<T, Error> {
+        let res = self.call_function(name, args).await?;
+        bson::from_bson(res).map_err(|x| Error::Deserialization(format!("{:?}", x)))
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize)]
+struct FunctionCallRequest {
+    name: String,
+    arguments: Vec<Bson>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bson::{Bson, DateTime, doc, oid::ObjectId};
+    use futures_executor::block_on;
+    use http::Method;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use crate::{Authentication, Client, Error, Session, Transport};
+    use crate::scripted::{Scripted, header, json_body};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Total {
+        id: ObjectId,
+        sum: i64,
+    }
+
+    #[test]
+    fn arguments_and_result_are_canonical_ejson() {
+        let id = ObjectId::new();
+        let transport = Scripted::new();
+        transport.respond(200, &format!(r#"{{"id":{{"$oid":"{}"}},"sum":{{"$numberLong":"7"}}}}"#, id.to_hex()));
+        let client = transport.client(Session::new("access", None));
+
+        let args = vec![Bson::ObjectId(id), Bson::Int64(3), Bson::DateTime(DateTime::from_millis(0)), doc! { "a": 1.5 }.into()];
+        let total = block_on(client.call_function_as::<Total>("sum", args)).unwrap();
+        assert_eq!(total, Total { id, sum: 7 });
+
+        let request = &transport.requests()[0];
+        assert_eq!(request.method, Method::POST);
+        assert_eq!(request.url, "https://realm.mongodb.com/api/client/v2.0/app/app/functions/call");
+        assert_eq!(header(request, "authorization"), Some("Bearer access"));
+        assert_eq!(json_body(request), json!({
+            "name": "sum",
+            "arguments": [
+                {"$oid": id.to_hex()},
+                {"$numberLong": "3"},
+                {"$date": {"$numberLong": "0"}},
+                {"a": {"$numberDouble": "1.5"}},
+            ],
+        }));
+    }
+
+    #[test]
+    fn results_of_the_wrong_type_fail() {
+        let transport = Scripted::new();
+        transport.respond(200, r#""text""#);
+        let client = transport.client(Session::new("access", None));
+        let res = block_on(client.call_function_as::<Total>("sum", Vec::new()));
+        assert!(matches!(res, Err(Error::Deserialization(..))), "{:?}", res);
+    }
+
+    #[test]
+    fn api_keys_are_rejected() {
+        let transport = Scripted::new();
+        let client = Client::new()
+            .application_id("app")
+            .authentication(Authentication::ApiKey("key".into()))
+            .transport(transport.clone() as Arc<dyn Transport>)
+            .build();
+        let res = block_on(client.call_function("sum", Vec::new()));
+        assert!(matches!(res, Err(Error::Auth { status: None, .. })), "{:?}", res);
+        assert!(transport.requests().is_empty());
+    }
+}
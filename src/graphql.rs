@@ -0,0 +1,193 @@
+use http::{Method, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::{Client, Error, HttpRequest};
+
+#[derive(Debug, Clone)]
+/// Builder of a query or mutation against the [GraphQL API](https://www.mongodb.com/docs/atlas/app-services/graphql/) of the app, see [Client::graphql]
+pub struct GraphQl<'a> {
+    client: &'a Client,
+    req: GraphQlRequest,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRequest {
+    query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    variables: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(bound(deserialize = "T: DeserializeOwned"))]
+/// The response of the GraphQL API, which may contain data as well as errors
+pub struct GraphQlResponse<T = serde_json::Value> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphQlError>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// An error of a GraphQL query
+pub struct GraphQlError {
+    pub message: String,
+    #[serde(default)]
+    pub locations: Vec<GraphQlLocation>,
+    /// the path of the field, which caused the error
+    #[serde(default)]
+    pub path: Vec<serde_json::Value>,
+    pub extensions: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct GraphQlLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl<T> GraphQlResponse<T> {
+    /// the data, or [Error::GraphQl] if the response contains errors or no data
+    pub fn into_result(self) -> Result<T, Error> {
+        match (self.data, self.errors.is_empty()) {
+            (Some(data), true) => Ok(data),
+            _ => Err(Error::GraphQl(self.errors)),
+        }
+    }
+}
+
+#[allow(unused)]
+impl Client {
+    /// gets the graphql url https://realm.mongodb.com/api/client/v2.0/app/<App ID>/graphql
+    fn get_graphql_url(&self) -> String {
+        format!("{}/app/{}/graphql", self.client_api_url(), self.application_id)
+    }
+
+    /// # Send a GraphQL Query or Mutation
+    ///
+    /// The request is authenticated like the data api actions.
+    pub fn graphql(&self, query: impl Into<String>) -> GraphQl<'_> {
+        GraphQl {
+            client: self,
+            req: GraphQlRequest { query: query.into(), variables: None, operation_name: None },
+        }
+    }
+}
+
+impl<'a> GraphQl<'a> {
+    /// the variables of the query, as a json object
+    pub fn variables(mut self, variables: serde_json::Value) -> Self {
+        self.req.variables = Some(variables);
+        self
+    }
+    /// selects the operation, if the query contains multiple
+    pub fn operation_name(mut self, operation_name: impl Into<String>) -> Self {
+        self.req.operation_name = Some(operation_name.into());
+        self
+    }
+
+    /// sends the request, the data is left as json
+    pub async fn send(self) -> Result<GraphQlResponse, Error> {
+        self.send_as().await
+    }
+    /// sends the request and deserializes the data into `T`
+    pub async fn send_as<T: DeserializeOwned>(self) -> Result<GraphQlResponse<T>, Error> {
+        self.client.resolve_location().await?;
+        let mut header_map = HeaderMap::new();
+        header_map.append(HeaderName::from_static("content-type"), HeaderValue::from_static("application/json"));
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("application/json"));
+
+        let res = self.client.send_authorized(HttpRequest {
+            method: Method::POST,
+            url: self.client.get_graphql_url(),
+            headers: header_map,
+            body: Some(serde_json::to_vec(&self.req).map_err(|x| Error::Serialization(format!("{:?}", x)))?),
+        }, false).await?;
+
+        serde_json::from_slice(&res.body).map_err(|x| Error::Deserialization(format!("{:?}", x)))
+    }
+    /// sends the request and returns only the data, failing if the response contains errors
+    pub async fn data<T: DeserializeOwned>(self) -> Result<T, Error> {
+        self.send_as::<T>().await?.into_result()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::block_on;
+    use http::Method;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    use super::GraphQlResponse;
+    use crate::{Error, Session};
+    use crate::scripted::{Scripted, header, json_body};
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Movie {
+        title: String,
+    }
+
+    #[test]
+    fn request_body() {
+        let transport = Scripted::new();
+        transport
+            .respond(200, r#"{"data":{"movie":{"title":"Alien"}}}"#)
+            .respond(200, r#"{"data":{"movie":{"title":"Alien"}}}"#);
+        let client = transport.client(Session::new("access", None));
+        block_on(client.graphql("query { movie { title } }").send()).unwrap();
+        let res = block_on(client.graphql("query A($year: Int) { movie(year: $year) { title } } query B { x }")
+            .variables(json!({"year": 1979}))
+            .operation_name("A")
+            .send_as::<serde_json::Value>()).unwrap();
+        assert_eq!(res.data, Some(json!({"movie": {"title": "Alien"}})));
+
+        let requests = transport.requests();
+        for request in &requests {
+            assert_eq!(request.method, Method::POST);
+            assert_eq!(request.url, "https://realm.mongodb.com/api/client/v2.0/app/app/graphql");
+            assert_eq!(header(request, "authorization"), Some("Bearer access"));
+            assert_eq!(header(request, "content-type"), Some("application/json"));
+        }
+        assert_eq!(json_body(&requests[0]), json!({"query": "query { movie { title } }"}));
+        assert_eq!(json_body(&requests[1]), json!({
+            "query": "query A($year: Int) { movie(year: $year) { title } } query B { x }",
+            "variables": {"year": 1979},
+            "operationName": "A",
+        }));
+    }
+
+    #[test]
+    fn errors_fail_into_result() {
+        let transport = Scripted::new();
+        transport
+            .respond(200, r#"{"data":{"title":"Alien"}}"#)
+            .respond(200, r#"{"data":null,"errors":[{"message":"no such field","locations":[{"line":1,"column":9}],"path":["movie",0]}]}"#)
+            .respond(200, r#"{"data":{"title":"Alien"},"errors":[{"message":"partial","extensions":{"code":"X"}}]}"#);
+        let client = transport.client(Session::new("access", None));
+
+        let movie = block_on(client.graphql("q").data::<Movie>()).unwrap();
+        assert_eq!(movie, Movie { title: "Alien".into() });
+
+        let res = block_on(client.graphql("q").data::<Movie>());
+        let errors = match res {
+            Err(Error::GraphQl(errors)) => errors,
+            x => panic!("{:?}", x),
+        };
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "no such field");
+        assert_eq!((errors[0].locations[0].line, errors[0].locations[0].column), (1, 9));
+        assert_eq!(errors[0].path, [json!("movie"), json!(0)]);
+
+        // the data is kept in the response, but into_result fails because of the errors
+        let res = block_on(client.graphql("q").send_as::<Movie>()).unwrap();
+        assert_eq!(res.data, Some(Movie { title: "Alien".into() }));
+        assert_eq!(res.errors[0].extensions, Some(json!({"code": "X"})));
+        assert!(matches!(res.into_result(), Err(Error::GraphQl(x)) if x[0].message == "partial"));
+
+        let empty = GraphQlResponse::<Movie> { data: None, errors: Vec::new() };
+        assert!(matches!(empty.into_result(), Err(Error::GraphQl(x)) if x.is_empty()));
+    }
+}
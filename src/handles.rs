@@ -0,0 +1,124 @@
+use bson::{Bson, Document};
+use futures_util::Stream;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Client, Collection, Error, TypedCollection, ChangeEvent, Update};
+use crate::actions::{FindOne, Find, InsertOne, InsertMany, UpdateOne, UpdateMany, ReplaceOne, DeleteOne, DeleteMany, Aggregate};
+
+#[derive(Debug, Clone)]
+/// An Atlas data source (linked cluster), bound to a [Client]
+pub struct DataSource {
+    client: Client,
+    name: String,
+}
+
+impl DataSource {
+    pub(crate) fn new(client: Client, name: String) -> Self {
+        DataSource { client, name }
+    }
+    /// the name of the data source, e.g. `mongodb-atlas`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// selects a database of this data source
+    pub fn database(&self, name: impl Into<String>) -> Database {
+        Database { client: self.client.clone(), data_source: self.name.clone(), name: name.into() }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A database, bound to a [Client]
+pub struct Database {
+    client: Client,
+    data_source: String,
+    name: String,
+}
+
+impl Database {
+    /// the name of the database
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// selects a collection of this database
+    pub fn collection(&self, name: impl Into<String>) -> CollectionHandle {
+        CollectionHandle {
+            client: self.client.clone(),
+            collection: Collection {
+                data_source: self.data_source.clone(),
+                database: self.name.clone(),
+                collection: name.into(),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A collection, bound to a [Client], which exposes all data api actions
+///
+/// The actions return the request builders of [Actions](crate::Actions), which are sent by awaiting them.
+pub struct CollectionHandle {
+    client: Client,
+    collection: Collection,
+}
+
+impl CollectionHandle {
+    /// the selected collection
+    pub fn collection(&self) -> &Collection {
+        &self.collection
+    }
+    /// the underlying client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+    /// a [TypedCollection] of this collection, which converts its documents from and into `T`
+    pub fn typed<T: Serialize + DeserializeOwned>(&self) -> TypedCollection<T> {
+        TypedCollection::new(self.client.clone(), self.collection.clone())
+    }
+
+    /// # Find a Single Document
+    pub fn find_one(&self) -> FindOne<'_> {
+        self.client.action().find_one(self.collection.clone())
+    }
+    /// # Find Multiple Documents
+    pub fn find(&self) -> Find<'_> {
+        self.client.action().find(self.collection.clone())
+    }
+    /// # Insert a Single Document
+    pub fn insert_one(&self, document: Document) -> InsertOne<'_> {
+        self.client.action().insert_one(self.collection.clone(), document)
+    }
+    /// # Insert Multiple Documents
+    pub fn insert(&self, documents: Vec<Document>) -> InsertMany<'_> {
+        self.client.action().insert(self.collection.clone(), documents)
+    }
+    /// # Update a Single Document
+    pub fn update_one(&self, filter: impl Into<Document>, update: impl Into<Update>) -> UpdateOne<'_> {
+        self.client.action().update_one(self.collection.clone(), filter, update)
+    }
+    /// # Update Multiple Documents
+    pub fn update(&self, filter: impl Into<Document>, update: impl Into<Update>) -> UpdateMany<'_> {
+        self.client.action().update(self.collection.clone(), filter, update)
+    }
+    /// # Replace a Single Document
+    pub fn replace_one(&self, filter: impl Into<Document>, replacement: Document) -> ReplaceOne<'_> {
+        self.client.action().replace_one(self.collection.clone(), filter, replacement)
+    }
+    /// # Delete a Single Document
+    pub fn delete_one(&self, filter: impl Into<Document>) -> DeleteOne<'_> {
+        self.client.action().delete_one(self.collection.clone(), filter)
+    }
+    /// # Delete Multiple Documents
+    pub fn delete(&self, filter: impl Into<Document>) -> DeleteMany<'_> {
+        self.client.action().delete(self.collection.clone(), filter)
+    }
+    /// # Run an Aggregation Pipeline
+    pub fn aggregate(&self, pipeline: impl Into<Vec<Document>>) -> Aggregate<'_> {
+        self.client.action().aggregate(self.collection.clone(), pipeline)
+    }
+    /// # Watch the Collection
+    ///
+    /// see [Client::watch]
+    pub fn watch(&self, filter: Option<Document>, ids: Option<Vec<Bson>>) -> impl Stream<Item = Result<ChangeEvent, Error>> + '_ {
+        self.client.watch(self.collection.clone(), filter, ids)
+    }
+}
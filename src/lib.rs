@@ -1,10 +1,61 @@
-use std::fmt::Display;
+use std::sync::Arc;
 
 pub use ::bson;
-use bson::{Document, oid::ObjectId};
+use bson::{Bson, Document};
 use builder_pattern::Builder;
-use reqwest::{StatusCode, header::{HeaderMap, HeaderName, HeaderValue}};
-use serde::{Serialize, Deserialize};
+use futures_util::Stream;
+use http::{Method, StatusCode, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+pub mod auth;
+pub use auth::{App, Authentication, Credentials, Session, Tokens};
+pub mod user;
+pub use user::{User, UserProfile};
+pub mod email_password;
+pub use email_password::EmailPasswordAuth;
+pub mod api_keys;
+pub use api_keys::ApiKeyAuth;
+pub mod typed;
+pub use typed::TypedCollection;
+pub mod ejson;
+pub use ejson::WireFormat;
+pub mod actions;
+pub use actions::Actions;
+use actions::{FindOne, Find, InsertOne, InsertMany, UpdateOne, UpdateMany, ReplaceOne, DeleteOne, DeleteMany, Aggregate};
+pub mod handles;
+pub use handles::{DataSource, Database, CollectionHandle};
+pub mod pagination;
+mod functions;
+pub mod watch;
+pub use watch::ChangeEvent;
+pub mod graphql;
+pub mod endpoint;
+pub mod query;
+pub use query::Filter;
+pub mod update;
+pub use update::Update;
+pub mod pipeline;
+pub use pipeline::Pipeline;
+pub mod model;
+pub use model::Model;
+#[cfg(feature="derive")]
+pub use realm_web_rs_derive::Model;
+pub mod storage;
+pub use storage::{SessionStorage, MemoryStorage};
+pub mod retry;
+pub use retry::RetryPolicy;
+pub mod location;
+pub use location::Location;
+pub mod error;
+pub use error::Error;
+pub mod transport;
+pub use transport::{Transport, HttpRequest, HttpResponse, StreamingResponse};
+#[cfg(feature = "reqwest")]
+pub use transport::ReqwestTransport;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(test)]
+mod scripted;
 
 #[derive(Builder, Debug, Clone)]
 /// Implements all the api calls, but doesn't hold information about the selected collection or database
@@ -13,16 +64,37 @@ pub struct Client {
     /// The application id, which is inserted into the query url
     pub application_id: String,
     #[into]
-    /// authentication using the `apiKey` header
-    pub api_token: String,
+    /// authentication using the `apiKey` header or the access token of a logged in user
+    pub authentication: Authentication,
 
     #[default(ApiVersion::v1)]
     pub api_version: ApiVersion,
+    #[default(WireFormat::Json)]
+    /// how documents are encoded in requests and responses
+    pub wire_format: WireFormat,
+    #[cfg_attr(feature = "reqwest", default(transport::default_transport()))]
+    /// sends the http requests, defaults to [ReqwestTransport] if the `reqwest` feature is enabled
+    pub transport: Arc<dyn Transport>,
+    #[default(RetryPolicy::none())]
+    /// retries failed requests, defaults to no retries
+    pub retry_policy: RetryPolicy,
     #[into]
     #[default(None)]
     /// should be none, if deployed globally
     /// or <Region>.<Cloud>
     pub deployment_region: Option<String>,
+    #[into]
+    #[default(None)]
+    /// overrides the hostname of all requests, e.g. for a private endpoint or a local server like `http://localhost:8080`
+    pub base_url: Option<String>,
+    #[default(false)]
+    /// queries the location of the app before the first request and uses its hostname instead of the deployment region,
+    /// ignored if a base url is set
+    pub discover_location: bool,
+    #[hidden]
+    #[default(Default::default())]
+    /// the discovered location; shared between all clones
+    location: location::LocationCache,
 }
 #[derive(Debug, Clone)]
 pub enum ApiVersion {
@@ -32,28 +104,114 @@ pub enum ApiVersion {
 #[allow(unused)]
 impl Client {
     /// gets base url https://data.mongodb-api.com/app/<App ID>/endpoint/data/<API Version>
-    fn get_url(&self) -> String {
-        format!(
-            "https://{}data.mongodb-api.com/app/{}/endpoint/data/{}",
-            match &self.deployment_region {
-                Some(x) => format!("{}.", x),
-                None => "".into()
-            },
-            self.application_id,
+    fn get_url(&self) -> Result<String, Error> {
+        self.get_endpoint_url(&format!(
+            "data/{}",
             match &self.api_version {
                 ApiVersion::v1 => "v1",
             }
-        )
+        ))
+    }
+    /// gets the client api url https://realm.mongodb.com/api/client/v2.0
+    pub(crate) fn client_api_url(&self) -> String {
+        self.location.client_api_url(&self.base_url, &self.deployment_region)
+    }
+    /// # Location
+    ///
+    /// the discovered location of the app, none until the first request if `discover_location` is set
+    pub fn location(&self) -> Option<Location> {
+        self.location.get()
+    }
+    /// discovers the location of the app, if enabled and not known yet
+    pub(crate) async fn resolve_location(&self) -> Result<(), Error> {
+        if self.discover_location && self.base_url.is_none() {
+            self.location.resolve(&self.application_id, &self.deployment_region, self.transport.as_ref()).await?;
+        }
+        Ok(())
+    }
+    /// creates a [TypedCollection] for the given collection, which converts its documents from and into `T`
+    pub fn typed_collection<T: Serialize + DeserializeOwned>(&self, collection: Collection) -> TypedCollection<T> {
+        TypedCollection::new(self.clone(), collection)
+    }
+    /// selects a data source (the name of the linked cluster, e.g. `mongodb-atlas`),
+    /// to continue with `.database("app").collection("users")`
+    pub fn data_source(&self, name: impl Into<String>) -> DataSource {
+        DataSource::new(self.clone(), name.into())
+    }
+    /// the fluent request builders of all actions, e.g.
+    /// `client.action().find(collection).filter(filter).limit(10).await`
+    pub fn action(&self) -> Actions<'_> {
+        Actions { client: self }
     }
-    /// gets the base headers
-    fn get_auth_headers(&self) -> HeaderMap {
+    /// gets the base headers, the authentication is added by [Client::send_authorized]
+    fn get_headers(&self) -> HeaderMap {
         let mut header_map = HeaderMap::new();
-        header_map.append(HeaderName::from_static("apikey"), HeaderValue::from_str(&self.api_token).unwrap());
-        header_map.append(HeaderName::from_static("content-type"), HeaderValue::from_static("application/json"));
-        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("application/json"));
+        header_map.append(HeaderName::from_static("content-type"), HeaderValue::from_static(self.wire_format.content_type()));
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static(self.wire_format.content_type()));
         header_map
     }
 
+    /// sends the request to the given action endpoint and deserializes the response
+    ///
+    /// Only idempotent requests are retried by default, see [RetryPolicy].
+    pub(crate) async fn send_action<Req: Serialize, Res: DeserializeOwned>(
+        &self,
+        action: &str,
+        req: &Req,
+        idempotent: bool
+    ) -> Result<Res, Error> {
+        self.resolve_location().await?;
+        let res = self.send_authorized(HttpRequest {
+            method: Method::POST,
+            url: format!("{}/action/{}", self.get_url()?, action),
+            headers: self.get_headers(),
+            body: Some(self.wire_format.encode(req)?.into_bytes()),
+        }, idempotent).await?;
+
+        self.wire_format.decode(&res.text())
+    }
+
+    /// sends the request with the authentication headers, fails if the status code isn't successful
+    ///
+    /// Transient failures are retried according to the [RetryPolicy], requests which aren't idempotent only if it allows so.
+    pub(crate) async fn send_authorized(&self, request: HttpRequest, idempotent: bool) -> Result<HttpResponse, Error> {
+        let policy = &self.retry_policy;
+        let retry = idempotent || policy.retry_non_idempotent;
+        let mut attempt = 1;
+        loop {
+            let res = self.send_refreshing(request.clone()).await;
+            let (transient, headers) = match &res {
+                Ok(x) if x.status.is_success() => return res,
+                Ok(x) => (policy.is_transient(x.status), Some(&x.headers)),
+                Err(x) => (policy.is_transient_error(x), None),
+            };
+            if !transient || !retry || attempt >= policy.max_attempts {
+                return res.and_then(|x| Err(Error::from_response(&x)));
+            }
+            let delay = policy.backoff(attempt, headers);
+            self.transport.sleep(delay).await;
+            attempt += 1;
+        }
+    }
+    /// sends the request once; if the access token of the session has expired, the session is refreshed and the request is sent once again.
+    async fn send_refreshing(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        let mut res = self.transport.send(self.authorize(request.clone())?).await?;
+
+        if res.status == StatusCode::UNAUTHORIZED {
+            if let Authentication::Bearer(session) = &self.authentication {
+                if session.can_refresh() {
+                    session.refresh(&self.client_api_url(), self.transport.as_ref()).await?;
+                    res = self.transport.send(self.authorize(request)?).await?;
+                }
+            }
+        }
+        Ok(res)
+    }
+    fn authorize(&self, mut request: HttpRequest) -> Result<HttpRequest, Error> {
+        self.authentication.append_headers(&mut request.headers)?;
+        Ok(request)
+    }
+
     /// # Find a Single Document
     /// 
     /// ### filter
@@ -67,8 +225,7 @@ impl Client {
         &self,
         collection: Collection,
         filter: Option<Document>,
-        projection: Option<Document>,
-        http_client: &reqwest::Client
+        projection: Option<Document>
     ) -> Result<FindResponse, Error> {
         let req = FindRequest {
             collection,
@@ -79,17 +236,7 @@ impl Client {
             skip: None
         };
 
-        let res = http_client.post(format!("{}/action/findOne", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<FindResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        FindOne { client: self, req }.send().await
     }
     /// # Find Multiple Documents
     /// ### filter
@@ -109,6 +256,7 @@ impl Client {
     ///
     /// ### skip
     /// The number of matched documents to skip before adding matched documents to the result set.
+    #[allow(clippy::too_many_arguments)]
     pub async fn find(
         &self,
         collection: Collection,
@@ -116,8 +264,7 @@ impl Client {
         projection: Option<Document>,
         sort: Option<Document>,
         limit: Option<i32>,
-        skip: Option<i32>,
-        http_client: &reqwest::Client
+        skip: Option<i32>
     ) -> Result<FindResponse, Error> {
         let req = FindRequest {
             collection,
@@ -128,17 +275,32 @@ impl Client {
             skip
         };
 
-        let res = http_client.post(format!("{}/action/find", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<FindResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        Find { client: self, req }.send().await
+    }
+    /// # Find Multiple Documents, page by page
+    ///
+    /// Returns a stream of all matching documents, which requests `page_size` documents at a time.
+    /// If the documents are sorted only by `_id` (or not sorted, then they are sorted by `_id`),
+    /// the next page continues after the last `_id`, otherwise it skips the already returned documents
+    /// and sorts by `_id` after the given sort, so documents with equal sort keys aren't repeated or missed.
+    /// see [Client::find]
+    pub fn find_stream(
+        &self,
+        collection: Collection,
+        filter: Option<Document>,
+        projection: Option<Document>,
+        sort: Option<Document>,
+        page_size: i32
+    ) -> impl Stream<Item = Result<Document, Error>> + '_ {
+        let req = FindRequest {
+            collection,
+            filter,
+            projection,
+            sort,
+            limit: None,
+            skip: None
+        };
+        Find { client: self, req }.stream(page_size)
     }
     /// # Insert a Single Document
     /// 
@@ -147,25 +309,14 @@ impl Client {
     pub async fn insert_one(
         &self,
         collection: Collection,
-        document: Document,
-        http_client: &reqwest::Client
+        document: Document
     ) -> Result<InsertResponse, Error> {
         let req = InsertRequest {
             collection,
             document: Some(document),
             documents: None
         };
-        let res = http_client.post(format!("{}/action/insertOne", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<InsertResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        InsertOne { client: self, req }.send().await
     } 
     /// # Insert Multiple Documents
     /// 
@@ -174,92 +325,59 @@ impl Client {
     pub async fn insert(
         &self,
         collection: Collection,
-        documents: Vec<Document>,
-        http_client: &reqwest::Client
+        documents: Vec<Document>
     ) -> Result<InsertResponse, Error> {
         let req = InsertRequest {
             collection,
             document: None,
             documents: Some(documents)
         };
-        let res = http_client.post(format!("{}/action/insertMany", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<InsertResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        InsertMany { client: self, req }.send().await
     }
     /// # Update a Single Document
     /// ### filter
     /// A [MongoDB Query Filter](https://www.mongodb.com/docs/manual/tutorial/query-documents/). The updateOne action modifies the first document in the collection that matches this filter.
     /// ### update
-    /// A [MongoDB Update Expression](https://www.mongodb.com/docs/manual/tutorial/update-documents/) that specifies how to modify the matched document.
+    /// A [MongoDB Update Expression](https://www.mongodb.com/docs/manual/tutorial/update-documents/) that specifies how to modify the matched document, see [Update].
     /// ### upsert
     /// The upsert flag only applies if no documents match the specified filter. If true, the updateOne action inserts a new document that matches the filter with the specified update applied to it.
     pub async fn update_one(
         &self,
         collection: Collection,
         filter: Document,
-        update: Document,
-        upsert: Option<bool>,
-        http_client: &reqwest::Client
+        update: impl Into<Update>,
+        upsert: Option<bool>
     ) -> Result<UpdateResponse, Error> {
         let req = UpdateRequest {
             collection,
             filter,
-            update,
+            update: update.into(),
             upsert
         };
-        let res = http_client.post(format!("{}/action/updateOne", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<UpdateResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        UpdateOne { client: self, req }.send().await
     }
     /// # Update Multiple Documents
     /// 
     /// ### filter
     /// A [MongoDB Query Filter](https://www.mongodb.com/docs/manual/tutorial/query-documents/). The updateMany action modifies the first document in the collection that matches this filter.
     /// ### update
-    /// A [MongoDB Update Expression](https://www.mongodb.com/docs/manual/tutorial/update-documents/) that specifies how to modify the matched document.
+    /// A [MongoDB Update Expression](https://www.mongodb.com/docs/manual/tutorial/update-documents/) that specifies how to modify the matched document, see [Update].
     /// ### upsert
     /// The upsert flag only applies if no documents match the specified filter. If true, the updateMany action inserts a new document that matches the filter with the specified update applied to it.
     pub async fn update(
         &self,
         collection: Collection,
         filter: Document,
-        update: Document,
-        upsert: Option<bool>,
-        http_client: &reqwest::Client
+        update: impl Into<Update>,
+        upsert: Option<bool>
     ) -> Result<UpdateResponse, Error> {
         let req = UpdateRequest {
             collection,
             filter,
-            update,
+            update: update.into(),
             upsert
         };
-        let res = http_client.post(format!("{}/action/updateMany", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<UpdateResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        UpdateMany { client: self, req }.send().await
     }
 
     /// # Replace a Single Document
@@ -274,8 +392,7 @@ impl Client {
         collection: Collection,
         filter: Document,
         replacement: Document,
-        upsert: Option<bool>,
-        http_client: &reqwest::Client
+        upsert: Option<bool>
     ) -> Result<ReplaceResponse, Error> {
         let req = ReplaceRequest {
             collection,
@@ -283,17 +400,7 @@ impl Client {
             replacement,
             upsert
         };
-        let res = http_client.post(format!("{}/action/replaceOne", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<ReplaceResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        ReplaceOne { client: self, req }.send().await
     }
     /// # Delete a Single Document
     /// 
@@ -302,24 +409,13 @@ impl Client {
     pub async fn delete_one(
         &self,
         collection: Collection,
-        filter: Document,
-        http_client: &reqwest::Client
+        filter: Document
     ) -> Result<DeleteResponse, Error> {
         let req = DeleteRequest {
             collection,
             filter,
         };
-        let res = http_client.post(format!("{}/action/deleteOne", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<DeleteResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        DeleteOne { client: self, req }.send().await
     }
     /// # Delete Multiple Documents
     /// 
@@ -328,50 +424,28 @@ impl Client {
     pub async fn delete(
         &self,
         collection: Collection,
-        filter: Document,
-        http_client: &reqwest::Client
+        filter: Document
     ) -> Result<DeleteResponse, Error> {
         let req = DeleteRequest {
             collection,
             filter,
         };
-        let res = http_client.post(format!("{}/action/deleteMany", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<DeleteResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        DeleteMany { client: self, req }.send().await
     }
     /// # Run an Aggregation Pipeline
     /// 
     /// ### pipeline
-    /// A [MongoDB Aggregation Pipeline](https://www.mongodb.com/docs/manual/core/aggregation-pipeline/).
+    /// A [MongoDB Aggregation Pipeline](https://www.mongodb.com/docs/manual/core/aggregation-pipeline/), see [Pipeline].
     pub async fn aggregate(
         &self,
         collection: Collection,
-        pipeline: Vec<Document>,
-        http_client: &reqwest::Client
+        pipeline: impl Into<Vec<Document>>
     ) -> Result<AggregationResponse, Error> {
         let req = AggregationRequest {
             collection,
-            pipeline,
+            pipeline: pipeline.into(),
         };
-        let res = http_client.post(format!("{}/action/aggregate", self.get_url()))
-            .headers(self.get_auth_headers())
-            .body(serde_json::to_string(&req).map_err(|x| Error {status_code: None, error: format!("Format error: {:?}", x)})?)
-            .send()
-            .await.map_err(|x| Error {status_code: None, error: format!("Failed to send request: {:?}", x)})?;
-
-        if !res.status().is_success(){
-            return Err(Error { status_code: Some(res.status()), error: format!("; content: {}", res.text().await.unwrap_or_default()) })
-        }
-
-        res.json::<AggregationResponse>().await.map_err(|x| Error {status_code: None, error: format!("Failed to deserialize response: {:?}", x)})
+        Aggregate { client: self, req }.send().await
     }
 }
 
@@ -398,6 +472,11 @@ struct FindRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     skip: Option<i32>
 }
+impl FindRequest {
+    fn new(collection: Collection) -> Self {
+        FindRequest { collection, filter: None, projection: None, sort: None, limit: None, skip: None }
+    }
+}
 
 #[allow(unused)]
 #[derive(Debug, Clone, Serialize)]
@@ -413,9 +492,11 @@ struct InsertRequest {
 
 #[allow(unused)]
 #[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// The ids are usually `ObjectId`s, but can be any value given as `_id`; with [WireFormat::Json] an `ObjectId` is read as its hex string
 pub struct InsertResponse {
-    pub inserted_id: Option<ObjectId>,
-    pub inserted_ids: Option<Vec<ObjectId>>
+    pub inserted_id: Option<Bson>,
+    pub inserted_ids: Option<Vec<Bson>>
 }
 
 #[allow(unused)]
@@ -424,7 +505,7 @@ pub struct InsertResponse {
 pub struct UpdateResponse {
     pub matched_count: i32,
     pub modified_count: i32,
-    pub upserted_id: Option<ObjectId>
+    pub upserted_id: Option<Bson>
 }
 
 #[allow(unused)]
@@ -433,7 +514,7 @@ pub struct UpdateResponse {
 pub struct ReplaceResponse {
     pub matched_count: i32,
     pub modified_count: i32,
-    pub upserted_id: Option<ObjectId>
+    pub upserted_id: Option<Bson>
 }
 
 #[allow(unused)]
@@ -450,7 +531,7 @@ struct UpdateRequest {
     #[serde(flatten)]
     collection: Collection,
     filter: Document,
-    update: Document,
+    update: Update,
     #[serde(skip_serializing_if = "Option::is_none")]
     upsert: Option<bool>,
 }
@@ -492,19 +573,6 @@ struct AggregationRequest {
     pipeline: Vec<Document>,
 }
 
-#[allow(unused)]
-#[derive(Debug, Clone)]
-pub struct Error {
-    /// A statuscode, only available if the request gets denied
-    status_code: Option<StatusCode>,
-    error: String,
-}
-impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "StatusCode: {:?}; {}", self.status_code, self.error)
-    }
-}
-
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 /// holds information which collection to select
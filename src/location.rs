@@ -0,0 +1,156 @@
+use std::sync::{Arc, RwLock};
+
+use http::{Method, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::Deserialize;
+
+use crate::{Error, HttpRequest, Transport, auth::get_client_api_url};
+
+/// the suffixes of the client api hostnames, which follow the <Region>.<Cloud> part of regional deployments
+const HOSTNAME_SUFFIXES: [&str; 2] = [".services.cloud.mongodb.com", ".realm.mongodb.com"];
+
+#[allow(unused)]
+#[derive(Debug, Clone, Deserialize)]
+/// Where an app is deployed, as returned by the location endpoint of the client api
+pub struct Location {
+    /// `GLOBAL` or `LOCAL`
+    pub deployment_model: String,
+    /// the region of the deployment, e.g. `US-VA`
+    pub location: String,
+    /// the hostname of the client api, e.g. https://us-east-1.aws.services.cloud.mongodb.com
+    pub hostname: String,
+    /// the hostname of the websocket connections
+    pub ws_hostname: String,
+}
+
+impl Location {
+    /// the <Region>.<Cloud> part of the hostname, e.g. `us-east-1.aws`; none for global deployments and other hosts
+    ///
+    /// Both the current `*.services.cloud.mongodb.com` and the former `*.realm.mongodb.com` hostnames are understood.
+    pub fn deployment_region(&self) -> Option<String> {
+        let hostname = self.hostname.trim_end_matches('/');
+        let hostname = hostname.split_once("://").map(|(_, x)| x).unwrap_or(hostname);
+        HOSTNAME_SUFFIXES.iter()
+            .find_map(|x| hostname.strip_suffix(x))
+            .filter(|x| !x.is_empty())
+            .map(|x| x.to_string())
+    }
+    /// true for local (single region) deployments
+    pub fn is_local(&self) -> bool {
+        self.deployment_model.eq_ignore_ascii_case("LOCAL")
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// the discovered location, shared between an app, its clones and its clients
+pub(crate) struct LocationCache(Arc<RwLock<Option<Location>>>);
+
+impl LocationCache {
+    pub(crate) fn get(&self) -> Option<Location> {
+        self.0.read().unwrap().clone()
+    }
+
+    /// queries https://realm.mongodb.com/api/client/v2.0/app/<App ID>/location, unless the location is known already
+    pub(crate) async fn resolve(
+        &self,
+        application_id: &str,
+        deployment_region: &Option<String>,
+        transport: &dyn Transport
+    ) -> Result<(), Error> {
+        if self.get().is_some() {
+            return Ok(());
+        }
+        let mut header_map = HeaderMap::new();
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("application/json"));
+
+        let res = transport.send(HttpRequest {
+            method: Method::GET,
+            url: format!("{}/app/{}/location", get_client_api_url(deployment_region), application_id),
+            headers: header_map,
+            body: None,
+        }).await?;
+
+        if !res.status.is_success(){
+            return Err(Error::from_response(&res))
+        }
+        let location = serde_json::from_slice::<Location>(&res.body).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+        *self.0.write().unwrap() = Some(location);
+        Ok(())
+    }
+
+    /// gets the client api url https://<Hostname>/api/client/v2.0
+    ///
+    /// The base url takes precedence over the discovered location, which takes precedence over the deployment region.
+    pub(crate) fn client_api_url(&self, base_url: &Option<String>, deployment_region: &Option<String>) -> String {
+        match (base_url, self.get()) {
+            (Some(x), _) => format!("{}/api/client/v2.0", x.trim_end_matches('/')),
+            (None, Some(x)) => format!("{}/api/client/v2.0", x.hostname.trim_end_matches('/')),
+            (None, None) => get_client_api_url(deployment_region),
+        }
+    }
+
+    /// gets the data api hostname https://<Region>.<Cloud>.data.mongodb-api.com, with the same precedence
+    ///
+    /// Fails for a local deployment, whose region can't be derived from its hostname, instead of using the global hostname.
+    pub(crate) fn data_api_hostname(&self, base_url: &Option<String>, deployment_region: &Option<String>) -> Result<String, Error> {
+        let region = match (base_url, self.get()) {
+            (Some(x), _) => return Ok(x.trim_end_matches('/').to_string()),
+            (None, Some(x)) => match x.deployment_region() {
+                None if x.is_local() => return Err(Error::Location(format!(
+                    "The region of the local deployment at {} is unknown, set the base url of the client", x.hostname
+                ))),
+                region => region,
+            },
+            (None, None) => deployment_region.clone(),
+        };
+        Ok(format!(
+            "https://{}data.mongodb-api.com",
+            match region {
+                Some(x) => format!("{}.", x),
+                None => "".into()
+            }
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Location, LocationCache};
+    use crate::Error;
+
+    fn location(deployment_model: &str, hostname: &str) -> Location {
+        Location { deployment_model: deployment_model.into(), location: "US-VA".into(), hostname: hostname.into(), ws_hostname: "".into() }
+    }
+
+    fn cache(location: Location) -> LocationCache {
+        let cache = LocationCache::default();
+        *cache.0.write().unwrap() = Some(location);
+        cache
+    }
+
+    #[test]
+    fn deployment_region_of_the_hostnames() {
+        for (hostname, region) in [
+            ("https://us-east-1.aws.services.cloud.mongodb.com", Some("us-east-1.aws")),
+            ("https://eu-west-1.aws.services.cloud.mongodb.com/", Some("eu-west-1.aws")),
+            ("https://us-east-1.aws.realm.mongodb.com", Some("us-east-1.aws")),
+            ("https://services.cloud.mongodb.com", None),
+            ("https://realm.mongodb.com", None),
+            ("https://example.com", None),
+        ] {
+            assert_eq!(location("LOCAL", hostname).deployment_region().as_deref(), region, "{}", hostname);
+        }
+    }
+
+    #[test]
+    fn data_api_hostname_of_the_location() {
+        let local = cache(location("LOCAL", "https://us-east-1.aws.services.cloud.mongodb.com"));
+        assert_eq!(local.data_api_hostname(&None, &None).unwrap(), "https://us-east-1.aws.data.mongodb-api.com");
+
+        let global = cache(location("GLOBAL", "https://services.cloud.mongodb.com"));
+        assert_eq!(global.data_api_hostname(&None, &None).unwrap(), "https://data.mongodb-api.com");
+
+        let unknown = cache(location("LOCAL", "https://example.com"));
+        assert!(matches!(unknown.data_api_hostname(&None, &None), Err(Error::Location(_))));
+        assert_eq!(unknown.data_api_hostname(&Some("https://example.com/".into()), &None).unwrap(), "https://example.com");
+    }
+}
@@ -0,0 +1,60 @@
+use bson::{Bson, Document, doc};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Client, Collection, TypedCollection};
+
+/// A type, whose values are stored as the documents of a collection
+///
+/// Usually implemented with `#[derive(Model)]` (feature `derive`), which also adds a constant with the document field name of every struct field:
+/// ```ignore
+/// #[derive(Serialize, Deserialize, Model)]
+/// #[model(data_source = "mongodb-atlas", database = "shop", collection = "users")]
+/// struct User {
+///     #[serde(rename = "_id")]
+///     id: ObjectId,
+///     name: String,
+///     age: u32,
+/// }
+///
+/// let users = client.model::<User>();
+/// let adults = users.find(Some(field(User::AGE).gte(18).into()), Some(User::projection()), None, None, None).await?;
+/// ```
+/// The `model` attribute takes the `data_source`, `database` and `collection` names; the field stored as `_id` is the id field,
+/// unless another field is marked with `#[model(id)]`. The `rename` and `rename_all` serde attributes are respected.
+/// Fields named like the constants of this trait, e.g. `collection`, are rejected, because their constants would shadow them.
+pub trait Model: Serialize + DeserializeOwned {
+    /// the name of the Atlas data source
+    const DATA_SOURCE: &'static str;
+    /// the name of the database
+    const DATABASE: &'static str;
+    /// the name of the collection
+    const COLLECTION: &'static str;
+    /// the document field name of the id, see [Model::id_filter]
+    const ID_FIELD: &'static str;
+    /// the document field names of all fields
+    const FIELDS: &'static [&'static str];
+
+    /// the collection storing the values
+    fn collection() -> Collection {
+        Collection {
+            data_source: Self::DATA_SOURCE.into(),
+            database: Self::DATABASE.into(),
+            collection: Self::COLLECTION.into(),
+        }
+    }
+    /// the projection including exactly the fields of the type
+    fn projection() -> Document {
+        Self::FIELDS.iter().map(|x| (x.to_string(), 1.into())).collect()
+    }
+    /// the filter matching the document with the id, e.g. for `find_one` or `delete_one`
+    fn id_filter(id: impl Into<Bson>) -> Document {
+        doc! { Self::ID_FIELD: id.into() }
+    }
+}
+
+impl Client {
+    /// a [TypedCollection] of the collection storing `T`
+    pub fn model<T: Model>(&self) -> TypedCollection<T> {
+        self.typed_collection(T::collection())
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::VecDeque;
+
+use bson::{Bson, Document, doc};
+use futures_util::{Stream, stream};
+
+use crate::{Client, Error, FindRequest, FindResponse};
+
+/// the maximum number of documents, which the data api returns per request
+pub const MAX_PAGE_SIZE: i32 = 50_000;
+
+/// how the next page is selected
+#[derive(Debug, Clone)]
+enum Cursor {
+    /// continues after the `_id` of the last document, the results are sorted by `_id`
+    IdRange { ascending: bool, last_id: Option<Bson> },
+    /// skips the already returned documents, the results are sorted by `_id` after the requested sort
+    Skip { offset: i64 },
+}
+
+struct State<'a> {
+    client: &'a Client,
+    req: FindRequest,
+    filter: Document,
+    page_size: i32,
+    cursor: Cursor,
+    /// the remaining number of documents, if a limit was set
+    remaining: Option<i64>,
+    buffer: VecDeque<Document>,
+    done: bool,
+}
+
+/// pages through the results of the find request
+///
+/// Results sorted only by `_id` (or not sorted at all, which then sorts by `_id`) are paged by the range of `_id`,
+/// everything else falls back to skip/limit, with `_id` appended to the sort, so the pages are stable.
+/// The limit and skip of the request apply to the whole stream.
+pub(crate) fn find_stream(client: &Client, mut req: FindRequest, page_size: i32) -> impl Stream<Item = Result<Document, Error>> + '_ {
+    let page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+    let excludes_id = req.projection.as_ref()
+        .and_then(|x| x.get("_id"))
+        .map(|x| direction(x).is_none() && !matches!(x, Bson::Boolean(true)))
+        .unwrap_or(false);
+
+    let mut sort = req.sort.take().unwrap_or_default();
+    let sorted_by_id = match (sort.len(), sort.get("_id")) {
+        _ if excludes_id => None,
+        (0, _) => Some(true),
+        (1, Some(x)) => direction(x),
+        _ => None,
+    };
+    let cursor = match sorted_by_id {
+        Some(ascending) => {
+            req.sort = Some(doc! {"_id": if ascending {1} else {-1}});
+            Cursor::IdRange { ascending, last_id: None }
+        },
+        None => {
+            if !sort.contains_key("_id") {
+                sort.insert("_id", 1);
+            }
+            req.sort = Some(sort);
+            Cursor::Skip { offset: req.skip.unwrap_or(0) as i64 }
+        },
+    };
+
+    let state = State {
+        client,
+        filter: req.filter.take().unwrap_or_default(),
+        remaining: req.limit.filter(|x| *x > 0).map(|x| x as i64),
+        req,
+        page_size,
+        cursor,
+        buffer: VecDeque::new(),
+        done: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(document) = state.buffer.pop_front() {
+                return Some((Ok(document), state));
+            }
+            if state.done || state.remaining == Some(0) {
+                return None;
+            }
+            if let Err(x) = state.next_page().await {
+                state.done = true;
+                return Some((Err(x), state));
+            }
+        }
+    })
+}
+
+impl<'a> State<'a> {
+    /// fetches the next page into the buffer
+    async fn next_page(&mut self) -> Result<(), Error> {
+        let limit = match self.remaining {
+            Some(x) => x.min(self.page_size as i64) as i32,
+            None => self.page_size,
+        };
+        let mut req = self.req.clone();
+        req.limit = Some(limit);
+
+        match &self.cursor {
+            Cursor::IdRange { ascending, last_id } => {
+                req.filter = Some(match last_id {
+                    Some(id) => {
+                        let range = doc! {"_id": {if *ascending {"$gt"} else {"$lt"}: id.clone()}};
+                        match self.filter.is_empty() {
+                            true => range,
+                            false => doc! {"$and": [self.filter.clone(), range]},
+                        }
+                    },
+                    None => self.filter.clone(),
+                });
+                if last_id.is_some() {
+                    req.skip = None;
+                }
+            },
+            Cursor::Skip { offset } => {
+                req.filter = Some(self.filter.clone());
+                req.skip = Some(*offset as i32);
+            },
+        }
+
+        let res: FindResponse = self.client.send_action("find", &req, true).await?;
+        let documents = res.documents.unwrap_or_default();
+
+        if (documents.len() as i64) < limit as i64 {
+            self.done = true;
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= documents.len() as i64;
+        }
+        match &mut self.cursor {
+            Cursor::IdRange { last_id, .. } => match documents.last().and_then(|x| x.get("_id")) {
+                Some(id) => *last_id = Some(id.clone()),
+                None => self.done = true,
+            },
+            Cursor::Skip { offset } => *offset += documents.len() as i64,
+        }
+        self.buffer.extend(documents);
+        Ok(())
+    }
+}
+
+/// true for an ascending, false for a descending sort direction, none for anything else (incl. 0)
+fn direction(value: &Bson) -> Option<bool> {
+    match value {
+        Bson::Int32(x) if *x != 0 => Some(*x > 0),
+        Bson::Int64(x) if *x != 0 => Some(*x > 0),
+        Bson::Double(x) if *x != 0.0 => Some(*x > 0.0),
+        _ => None,
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use bson::{Bson, Document, doc};
+    use futures_executor::block_on;
+    use futures_util::StreamExt;
+
+    use crate::{Client, Collection, Error, HttpRequest, HttpResponse, Transport, WireFormat, ejson, testing::MockDataApi, transport::BoxFuture};
+
+    /// forwards to the mock and records the bodies of the find requests
+    #[derive(Debug)]
+    struct Recorder {
+        mock: Arc<MockDataApi>,
+        requests: Mutex<Vec<Document>>,
+    }
+
+    impl Transport for Recorder {
+        fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+            let body: serde_json::Value = serde_json::from_slice(request.body.as_deref().unwrap_or_default()).unwrap();
+            if let Ok(Bson::Document(x)) = ejson::from_extjson(body) {
+                self.requests.lock().unwrap().push(x);
+            }
+            self.mock.send(request)
+        }
+    }
+
+    fn collection() -> Collection {
+        Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "c".into() }
+    }
+
+    /// documents with the ids 1 to 7, the ages repeat
+    fn setup() -> (Arc<Recorder>, Client) {
+        let mock = Arc::new(MockDataApi::new());
+        mock.insert_documents(&collection(), (1..=7).rev().map(|x| doc! {"_id": x, "age": x % 3}));
+        let recorder = Arc::new(Recorder { mock, requests: Mutex::new(Vec::new()) });
+        let client = Client::new()
+            .application_id("mock")
+            .authentication(crate::Authentication::ApiKey("mock".into()))
+            .wire_format(WireFormat::CanonicalEjson)
+            .transport(recorder.clone() as Arc<dyn Transport>)
+            .build();
+        (recorder, client)
+    }
+
+    fn collect(client: &Client, projection: Option<Document>, sort: Option<Document>, page_size: i32) -> Vec<Document> {
+        block_on(client.find_stream(collection(), None, projection, sort, page_size).map(|x| x.unwrap()).collect::<Vec<_>>())
+    }
+
+    fn ids(documents: &[Document]) -> Vec<i32> {
+        documents.iter().map(|x| x.get_i32("_id").unwrap()).collect()
+    }
+
+    #[test]
+    fn pages_by_id_range() {
+        let (recorder, client) = setup();
+        assert_eq!(ids(&collect(&client, None, None, 3)), [1, 2, 3, 4, 5, 6, 7]);
+
+        let requests = recorder.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|x| x.get_document("sort") == Ok(&doc! {"_id": 1}) && x.get("skip").is_none()));
+        assert_eq!(requests[1].get_document("filter"), Ok(&doc! {"_id": {"$gt": 3}}));
+        assert_eq!(requests[2].get_document("filter"), Ok(&doc! {"_id": {"$gt": 6}}));
+    }
+
+    #[test]
+    fn pages_descending_ids_with_limit_and_skip() {
+        let (_, client) = setup();
+        let stream = client.action().find(collection()).sort(doc! {"_id": -1}).skip(1).limit(4).stream(3);
+        let documents = block_on(stream.map(|x| x.unwrap()).collect::<Vec<_>>());
+        assert_eq!(ids(&documents), [6, 5, 4, 3]);
+    }
+
+    #[test]
+    fn skip_mode_sorts_by_id_when_it_is_projected_out() {
+        let (recorder, client) = setup();
+        let documents = collect(&client, Some(doc! {"_id": 0}), None, 2);
+        assert_eq!(documents.iter().map(|x| x.get_i32("age").unwrap()).collect::<Vec<_>>(), [1, 2, 0, 1, 2, 0, 1]);
+        assert!(documents.iter().all(|x| !x.contains_key("_id")));
+
+        let requests = recorder.requests.lock().unwrap();
+        assert_eq!(requests.iter().map(|x| x.get_i32("skip").unwrap()).collect::<Vec<_>>(), [0, 2, 4, 6]);
+        assert!(requests.iter().all(|x| x.get_document("sort") == Ok(&doc! {"_id": 1})));
+    }
+
+    #[test]
+    fn skip_mode_breaks_ties_by_id() {
+        let (recorder, client) = setup();
+        let documents = collect(&client, None, Some(doc! {"age": 1}), 2);
+        assert_eq!(ids(&documents), [3, 6, 1, 4, 7, 2, 5]);
+
+        let requests = recorder.requests.lock().unwrap();
+        assert!(requests.iter().all(|x| x.get_document("sort") == Ok(&doc! {"age": 1, "_id": 1})));
+
+        drop(requests);
+        let documents = collect(&client, None, Some(doc! {"age": -1, "_id": -1}), 4);
+        assert_eq!(ids(&documents), [5, 2, 7, 4, 1, 6, 3]);
+        assert_eq!(recorder.requests.lock().unwrap().last().unwrap().get_document("sort"), Ok(&doc! {"age": -1, "_id": -1}));
+    }
+}
@@ -0,0 +1,361 @@
+use bson::{Bson, Document};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A [MongoDB Aggregation Pipeline](https://www.mongodb.com/docs/manual/core/aggregation-pipeline/)
+///
+/// The stages are appended in the order of the calls, the pipeline converts into the `Vec<Document>` taken by the aggregate actions.
+pub struct Pipeline(Vec<Document>);
+
+impl Pipeline {
+    /// an empty pipeline, which returns all documents
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// appends a stage, e.g. one without a method of its own
+    pub fn stage(mut self, name: &str, argument: impl Into<Bson>) -> Self {
+        let mut stage = Document::new();
+        stage.insert(name, argument.into());
+        self.0.push(stage);
+        self
+    }
+
+    /// keeps the documents matching the [query filter](crate::query) (`$match`)
+    pub fn match_(self, filter: impl Into<Document>) -> Self {
+        self.stage("$match", filter.into())
+    }
+    /// includes, excludes or computes fields (`$project`)
+    pub fn project(self, projection: Document) -> Self {
+        self.stage("$project", projection)
+    }
+    /// groups the documents by the `_id` expression and computes the accumulators per group (`$group`)
+    pub fn group(self, id: impl Into<Bson>, accumulators: Accumulators) -> Self {
+        let mut group = Document::new();
+        group.insert("_id", id.into());
+        group.extend(accumulators.0);
+        self.stage("$group", group)
+    }
+    /// sorts the documents by the fields, `1` is ascending and `-1` descending (`$sort`)
+    pub fn sort(self, sort: impl Into<Document>) -> Self {
+        self.stage("$sort", sort.into())
+    }
+    /// keeps the first `limit` documents (`$limit`)
+    pub fn limit(self, limit: i64) -> Self {
+        self.stage("$limit", limit)
+    }
+    /// skips the first `skip` documents (`$skip`)
+    pub fn skip(self, skip: i64) -> Self {
+        self.stage("$skip", skip)
+    }
+    /// outputs one document per element of the array at `path`, e.g. `"$items"` (`$unwind`)
+    pub fn unwind(self, path: impl Into<String>) -> Self {
+        self.stage("$unwind", path.into())
+    }
+    /// like [Pipeline::unwind], but also outputs documents whose array is missing, null or empty, and stores the index of the element
+    /// ### include_array_index
+    /// the field storing the index of the element
+    pub fn unwind_preserving(self, path: impl Into<String>, include_array_index: Option<&str>) -> Self {
+        let mut unwind = Document::new();
+        unwind.insert("path", path.into());
+        if let Some(field) = include_array_index {
+            unwind.insert("includeArrayIndex", field);
+        }
+        unwind.insert("preserveNullAndEmptyArrays", true);
+        self.stage("$unwind", unwind)
+    }
+    /// joins the documents of `from`, whose `foreign_field` equals `local_field`, as the array `as_` (`$lookup`)
+    pub fn lookup(self, from: impl Into<String>, local_field: impl Into<String>, foreign_field: impl Into<String>, as_: impl Into<String>) -> Self {
+        let mut lookup = Document::new();
+        lookup.insert("from", from.into());
+        lookup.insert("localField", local_field.into());
+        lookup.insert("foreignField", foreign_field.into());
+        lookup.insert("as", as_.into());
+        self.stage("$lookup", lookup)
+    }
+    /// joins the output of the pipeline run on `from` as the array `as_` (`$lookup`)
+    /// ### let_
+    /// the variables of the pipeline, e.g. `{"order_id": "$_id"}` used as `$$order_id`
+    pub fn lookup_pipeline(self, from: impl Into<String>, let_: Document, pipeline: Pipeline, as_: impl Into<String>) -> Self {
+        let mut lookup = Document::new();
+        lookup.insert("from", from.into());
+        if !let_.is_empty() {
+            lookup.insert("let", let_);
+        }
+        lookup.insert("pipeline", pipeline.into_bson());
+        lookup.insert("as", as_.into());
+        self.stage("$lookup", lookup)
+    }
+    /// runs multiple pipelines on the same documents, each output is stored in an array field (`$facet`)
+    pub fn facet<N: Into<String>>(self, facets: impl IntoIterator<Item = (N, Pipeline)>) -> Self {
+        let facets = facets.into_iter().map(|(name, pipeline)| (name.into(), pipeline.into_bson())).collect::<Document>();
+        self.stage("$facet", facets)
+    }
+    /// adds fields computed by the expressions (`$addFields`)
+    pub fn add_fields(self, fields: Document) -> Self {
+        self.stage("$addFields", fields)
+    }
+    /// outputs a single document with the number of documents in the field (`$count`)
+    pub fn count(self, field: impl Into<String>) -> Self {
+        self.stage("$count", field.into())
+    }
+    /// groups the documents into buckets by ranges of the expression (`$bucket`)
+    /// ### boundaries
+    /// the ascending lower bounds of the buckets, the last one is the exclusive upper bound
+    /// ### default
+    /// the bucket `_id` of the documents outside the boundaries; without one these documents fail the aggregation
+    /// ### output
+    /// the fields of the buckets, defaults to the `count`
+    pub fn bucket<B: Into<Bson>>(
+        self,
+        group_by: impl Into<Bson>,
+        boundaries: impl IntoIterator<Item = B>,
+        default: Option<Bson>,
+        output: Option<Accumulators>
+    ) -> Self {
+        let mut bucket = Document::new();
+        bucket.insert("groupBy", group_by.into());
+        bucket.insert("boundaries", boundaries.into_iter().map(Into::into).collect::<Vec<Bson>>());
+        if let Some(default) = default {
+            bucket.insert("default", default);
+        }
+        if let Some(output) = output {
+            bucket.insert("output", output.0);
+        }
+        self.stage("$bucket", bucket)
+    }
+
+    /// the stages of the pipeline
+    pub fn stages(&self) -> &[Document] {
+        &self.0
+    }
+    fn into_bson(self) -> Bson {
+        Bson::Array(self.0.into_iter().map(Bson::Document).collect())
+    }
+}
+
+impl From<Pipeline> for Vec<Document> {
+    fn from(value: Pipeline) -> Self {
+        value.0
+    }
+}
+impl From<Vec<Document>> for Pipeline {
+    fn from(value: Vec<Document>) -> Self {
+        Pipeline(value)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// The computed fields of [Pipeline::group] and [Pipeline::bucket]
+///
+/// The expressions are usually field paths like `"$amount"` or constants.
+pub struct Accumulators(Document);
+
+impl Accumulators {
+    pub fn new() -> Self {
+        Accumulators::default()
+    }
+
+    /// computes the field with any [accumulator operator](https://www.mongodb.com/docs/manual/reference/operator/aggregation/group/#accumulator-operator), e.g. `"$stdDevPop"`
+    pub fn accumulator(mut self, field: impl Into<String>, operator: &str, expression: impl Into<Bson>) -> Self {
+        let mut accumulator = Document::new();
+        accumulator.insert(operator, expression.into());
+        self.0.insert(field, accumulator);
+        self
+    }
+    /// the sum of the expression (`$sum`)
+    pub fn sum(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$sum", expression)
+    }
+    /// the number of documents (`$sum: 1`)
+    pub fn count(self, field: impl Into<String>) -> Self {
+        self.accumulator(field, "$sum", 1)
+    }
+    /// the average of the expression (`$avg`)
+    pub fn avg(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$avg", expression)
+    }
+    /// the minimum of the expression (`$min`)
+    pub fn min(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$min", expression)
+    }
+    /// the maximum of the expression (`$max`)
+    pub fn max(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$max", expression)
+    }
+    /// the expression of the first document (`$first`)
+    pub fn first(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$first", expression)
+    }
+    /// the expression of the last document (`$last`)
+    pub fn last(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$last", expression)
+    }
+    /// an array of the expression of all documents (`$push`)
+    pub fn push(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$push", expression)
+    }
+    /// an array of the distinct values of the expression (`$addToSet`)
+    pub fn add_to_set(self, field: impl Into<String>, expression: impl Into<Bson>) -> Self {
+        self.accumulator(field, "$addToSet", expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::{Bson, doc};
+
+    use super::{Accumulators, Pipeline};
+    use crate::query::field;
+
+    #[test]
+    fn stages_produce_their_documents() {
+        let pipeline = Pipeline::new()
+            .match_(field("age").gte(18))
+            .project(doc! { "name": 1 })
+            .sort(doc! { "age": -1 })
+            .skip(10)
+            .limit(5)
+            .unwind("$tags")
+            .add_fields(doc! { "city": "$address.city" })
+            .lookup("orders", "_id", "customer", "orders")
+            .count("n")
+            .stage("$sample", doc! { "size": 3 });
+        assert_eq!(pipeline.stages(), [
+            doc! { "$match": { "age": { "$gte": 18 } } },
+            doc! { "$project": { "name": 1 } },
+            doc! { "$sort": { "age": -1 } },
+            doc! { "$skip": 10_i64 },
+            doc! { "$limit": 5_i64 },
+            doc! { "$unwind": "$tags" },
+            doc! { "$addFields": { "city": "$address.city" } },
+            doc! { "$lookup": { "from": "orders", "localField": "_id", "foreignField": "customer", "as": "orders" } },
+            doc! { "$count": "n" },
+            doc! { "$sample": { "size": 3 } },
+        ]);
+        assert_eq!(Vec::from(pipeline.clone()), pipeline.stages());
+        assert_eq!(Pipeline::from(pipeline.stages().to_vec()), pipeline);
+    }
+
+    #[test]
+    fn group_with_every_accumulator() {
+        let accumulators = Accumulators::new()
+            .sum("total", "$amount")
+            .count("n")
+            .avg("avg", "$amount")
+            .min("min", "$amount")
+            .max("max", "$amount")
+            .first("first", "$_id")
+            .last("last", "$_id")
+            .push("ids", "$_id")
+            .add_to_set("tags", "$tag")
+            .accumulator("dev", "$stdDevPop", "$amount");
+        assert_eq!(Pipeline::new().group("$team", accumulators).stages(), [doc! { "$group": {
+            "_id": "$team",
+            "total": { "$sum": "$amount" },
+            "n": { "$sum": 1 },
+            "avg": { "$avg": "$amount" },
+            "min": { "$min": "$amount" },
+            "max": { "$max": "$amount" },
+            "first": { "$first": "$_id" },
+            "last": { "$last": "$_id" },
+            "ids": { "$push": "$_id" },
+            "tags": { "$addToSet": "$tag" },
+            "dev": { "$stdDevPop": "$amount" },
+        } }]);
+        assert_eq!(Pipeline::new().group(Bson::Null, Accumulators::new()).stages(), [doc! { "$group": { "_id": null } }]);
+    }
+
+    #[test]
+    fn unwind_preserving() {
+        assert_eq!(Pipeline::new().unwind_preserving("$tags", None).unwind_preserving("$items", Some("i")).stages(), [
+            doc! { "$unwind": { "path": "$tags", "preserveNullAndEmptyArrays": true } },
+            doc! { "$unwind": { "path": "$items", "includeArrayIndex": "i", "preserveNullAndEmptyArrays": true } },
+        ]);
+    }
+
+    #[test]
+    fn lookup_pipeline() {
+        let orders = Pipeline::new().match_(doc! { "$expr": { "$eq": ["$customer", "$$id"] } }).limit(1);
+        assert_eq!(Pipeline::new()
+            .lookup_pipeline("orders", doc! { "id": "$_id" }, orders.clone(), "orders")
+            .lookup_pipeline("stock", doc! {}, Pipeline::new(), "stock")
+            .stages(), [
+            doc! { "$lookup": {
+                "from": "orders",
+                "let": { "id": "$_id" },
+                "pipeline": [{ "$match": { "$expr": { "$eq": ["$customer", "$$id"] } } }, { "$limit": 1_i64 }],
+                "as": "orders",
+            } },
+            doc! { "$lookup": { "from": "stock", "pipeline": [], "as": "stock" } },
+        ]);
+    }
+
+    #[test]
+    fn facet() {
+        let pipeline = Pipeline::new().facet([
+            ("total", Pipeline::new().count("n")),
+            ("top", Pipeline::new().sort(doc! { "score": -1 }).limit(3)),
+        ]);
+        assert_eq!(pipeline.stages(), [doc! { "$facet": {
+            "total": [{ "$count": "n" }],
+            "top": [{ "$sort": { "score": -1 } }, { "$limit": 3_i64 }],
+        } }]);
+    }
+
+    #[test]
+    fn bucket() {
+        assert_eq!(Pipeline::new()
+            .bucket("$age", [0, 18, 65], None, None)
+            .bucket("$price", [0.0, 100.0], Some(Bson::from("other")), Some(Accumulators::new().count("n").push("titles", "$title")))
+            .stages(), [
+            doc! { "$bucket": { "groupBy": "$age", "boundaries": [0, 18, 65] } },
+            doc! { "$bucket": {
+                "groupBy": "$price",
+                "boundaries": [0.0, 100.0],
+                "default": "other",
+                "output": { "n": { "$sum": 1 }, "titles": { "$push": "$title" } },
+            } },
+        ]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[test]
+    fn aggregate_through_the_mock() {
+        use std::sync::Arc;
+
+        use crate::{Collection, WireFormat, testing::MockDataApi};
+
+        let collection = Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "sales".into() };
+        let mock = Arc::new(MockDataApi::new());
+        mock.insert_documents(&collection, vec![
+            doc! { "_id": 1, "team": "a", "amount": 3, "tags": ["x", "y"] },
+            doc! { "_id": 2, "team": "b", "amount": 5, "tags": ["y"] },
+            doc! { "_id": 3, "team": "a", "amount": 4, "tags": [] },
+            doc! { "_id": 4, "team": "c", "amount": 1 },
+        ]);
+        let mut client = mock.client();
+        client.wire_format = WireFormat::CanonicalEjson;
+
+        let pipeline = Pipeline::new()
+            .match_(field("amount").gt(1))
+            .group("$team", Accumulators::new().sum("total", "$amount").count("n").push("ids", "$_id"))
+            .sort(doc! { "total": -1 });
+        let res = futures_executor::block_on(client.aggregate(collection.clone(), pipeline)).unwrap();
+        assert_eq!(res.documents, [
+            doc! { "_id": "a", "total": 7, "n": 2, "ids": [1, 3] },
+            doc! { "_id": "b", "total": 5, "n": 1, "ids": [2] },
+        ]);
+
+        let pipeline = Pipeline::new()
+            .unwind_preserving("$tags", Some("i"))
+            .project(doc! { "tags": 1, "i": 1 })
+            .skip(1)
+            .limit(3);
+        let res = futures_executor::block_on(client.aggregate(collection, pipeline)).unwrap();
+        assert_eq!(res.documents, [
+            doc! { "_id": 1, "tags": "y", "i": 1_i64 },
+            doc! { "_id": 2, "tags": "y", "i": 0_i64 },
+            doc! { "_id": 3, "tags": [], "i": null },
+        ]);
+    }
+}
@@ -0,0 +1,286 @@
+use std::{marker::PhantomData, ops::Not};
+
+use bson::{Bson, Document, Regex};
+
+/// # Filter a Field
+///
+/// Starts a condition on the field, the operators of the condition are chained,
+/// e.g. `field("age").gte(18).lt(65).and(field("tags").in_(["admin", "staff"]))`.
+/// The field names of a derived model can be used instead of string literals, to have them checked by the compiler.
+/// The field can only be used as a filter or negated, after an operator got applied.
+pub fn field(name: impl Into<String>) -> Field<NoOperators> {
+    Field { name: name.into(), condition: Document::new(), state: PhantomData }
+}
+
+/// matches documents matching all of the filters (`$and`)
+pub fn and<F: Into<Filter>>(filters: impl IntoIterator<Item = F>) -> Filter {
+    Filter::logical("$and", filters)
+}
+
+/// matches documents matching any of the filters (`$or`)
+pub fn or<F: Into<Filter>>(filters: impl IntoIterator<Item = F>) -> Filter {
+    Filter::logical("$or", filters)
+}
+
+/// matches documents matching none of the filters (`$nor`)
+pub fn nor<F: Into<Filter>>(filters: impl IntoIterator<Item = F>) -> Filter {
+    Filter::logical("$nor", filters)
+}
+
+/// matches documents, for which the [aggregation expression](https://www.mongodb.com/docs/manual/reference/operator/query/expr/) is true (`$expr`)
+pub fn expr(expression: impl Into<Bson>) -> Filter {
+    let mut filter = Document::new();
+    filter.insert("$expr", expression.into());
+    Filter(filter)
+}
+
+/// matches documents containing the search string in their text index (`$text`)
+pub fn text(search: impl Into<String>) -> Filter {
+    let mut text = Document::new();
+    text.insert("$search", search.into());
+    let mut filter = Document::new();
+    filter.insert("$text", text);
+    Filter(filter)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+/// A [MongoDB Query Filter](https://www.mongodb.com/docs/manual/tutorial/query-documents/)
+///
+/// Filters convert into the `Document` taken by the actions, the default filter matches all documents.
+pub struct Filter(Document);
+
+impl Filter {
+    /// the filter matching all documents
+    pub fn all() -> Self {
+        Filter::default()
+    }
+    /// matches documents matching both filters
+    pub fn and(self, other: impl Into<Filter>) -> Filter {
+        self.combine("$and", other.into())
+    }
+    /// matches documents matching either filter
+    pub fn or(self, other: impl Into<Filter>) -> Filter {
+        self.combine("$or", other.into())
+    }
+    /// the filter document
+    pub fn document(&self) -> &Document {
+        &self.0
+    }
+    /// converts the filter into its document
+    pub fn into_document(self) -> Document {
+        self.0
+    }
+
+    fn logical<F: Into<Filter>>(operator: &str, filters: impl IntoIterator<Item = F>) -> Filter {
+        let filters = filters.into_iter().map(|x| Bson::Document(x.into().0)).collect::<Vec<_>>();
+        let mut filter = Document::new();
+        filter.insert(operator, filters);
+        Filter(filter)
+    }
+    /// appends to an existing `$and`/`$or` instead of nesting them
+    fn combine(self, operator: &str, other: Filter) -> Filter {
+        let mut filters = match self.0.get_array(operator) {
+            Ok(x) if self.0.len() == 1 => x.clone(),
+            _ => vec![Bson::Document(self.0)],
+        };
+        filters.push(Bson::Document(other.0));
+        let mut filter = Document::new();
+        filter.insert(operator, filters);
+        Filter(filter)
+    }
+}
+
+impl From<Filter> for Document {
+    fn from(value: Filter) -> Self {
+        value.0
+    }
+}
+impl From<Filter> for Bson {
+    fn from(value: Filter) -> Self {
+        Bson::Document(value.0)
+    }
+}
+impl From<Document> for Filter {
+    fn from(value: Document) -> Self {
+        Filter(value)
+    }
+}
+impl From<Field> for Filter {
+    fn from(value: Field) -> Self {
+        let mut filter = Document::new();
+        filter.insert(value.name, value.condition);
+        Filter(filter)
+    }
+}
+impl From<Field> for Document {
+    fn from(value: Field) -> Self {
+        Filter::from(value).0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A condition on a single field, see [field]
+///
+/// `!field` negates the operators given so far (`$not`).
+/// A [Field] without operators can't be converted into a filter, it would match the documents, whose value is an empty document.
+pub struct Field<S = WithOperators> {
+    name: String,
+    condition: Document,
+    state: PhantomData<S>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// the state of a [Field], before an operator got applied
+pub struct NoOperators;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// the state of a [Field], after an operator got applied
+pub struct WithOperators;
+
+impl Not for Field {
+    type Output = Field;
+
+    fn not(mut self) -> Self::Output {
+        let condition = std::mem::take(&mut self.condition);
+        self.operator("$not", condition)
+    }
+}
+
+impl<S> Field<S> {
+    fn operator(mut self, operator: &str, value: impl Into<Bson>) -> Field {
+        self.condition.insert(operator, value.into());
+        Field { name: self.name, condition: self.condition, state: PhantomData }
+    }
+
+    /// the value equals `value` (`$eq`)
+    pub fn eq(self, value: impl Into<Bson>) -> Field {
+        self.operator("$eq", value)
+    }
+    /// the value doesn't equal `value` (`$ne`)
+    pub fn ne(self, value: impl Into<Bson>) -> Field {
+        self.operator("$ne", value)
+    }
+    /// the value is greater than `value` (`$gt`)
+    pub fn gt(self, value: impl Into<Bson>) -> Field {
+        self.operator("$gt", value)
+    }
+    /// the value is greater than or equal to `value` (`$gte`)
+    pub fn gte(self, value: impl Into<Bson>) -> Field {
+        self.operator("$gte", value)
+    }
+    /// the value is less than `value` (`$lt`)
+    pub fn lt(self, value: impl Into<Bson>) -> Field {
+        self.operator("$lt", value)
+    }
+    /// the value is less than or equal to `value` (`$lte`)
+    pub fn lte(self, value: impl Into<Bson>) -> Field {
+        self.operator("$lte", value)
+    }
+    /// the value is one of `values` (`$in`)
+    pub fn in_<V: Into<Bson>>(self, values: impl IntoIterator<Item = V>) -> Field {
+        self.operator("$in", values.into_iter().map(Into::into).collect::<Vec<Bson>>())
+    }
+    /// the value is none of `values` (`$nin`)
+    pub fn nin<V: Into<Bson>>(self, values: impl IntoIterator<Item = V>) -> Field {
+        self.operator("$nin", values.into_iter().map(Into::into).collect::<Vec<Bson>>())
+    }
+    /// the field is present, or absent if `exists` is false (`$exists`)
+    pub fn exists(self, exists: bool) -> Field {
+        self.operator("$exists", exists)
+    }
+    /// the value has the [BSON type](https://www.mongodb.com/docs/manual/reference/operator/query/type/), given by its alias or number (`$type`)
+    pub fn type_(self, bson_type: impl Into<Bson>) -> Field {
+        self.operator("$type", bson_type)
+    }
+    /// the string value matches the regular expression with the options, e.g. `"i"` (`$regex`)
+    pub fn regex(self, pattern: impl Into<String>, options: impl Into<String>) -> Field {
+        self.operator("$regex", Regex { pattern: pattern.into(), options: options.into() })
+    }
+    /// the value divided by `divisor` has the remainder `remainder` (`$mod`)
+    pub fn mod_(self, divisor: i64, remainder: i64) -> Field {
+        self.operator("$mod", vec![Bson::Int64(divisor), Bson::Int64(remainder)])
+    }
+    /// the array contains all of `values` (`$all`)
+    pub fn all<V: Into<Bson>>(self, values: impl IntoIterator<Item = V>) -> Field {
+        self.operator("$all", values.into_iter().map(Into::into).collect::<Vec<Bson>>())
+    }
+    /// the array has `size` elements (`$size`)
+    pub fn size(self, size: i64) -> Field {
+        self.operator("$size", size)
+    }
+    /// an element of the array matches the filter (`$elemMatch`)
+    ///
+    /// For arrays of documents, the filter uses the field names of the elements.
+    /// Conditions on the elements themselves are given by a field without name, e.g. `field("").gte(80).lt(85)`.
+    pub fn elem_match(self, filter: impl Into<Filter>) -> Field {
+        let filter = filter.into().0;
+        let filter = match filter.get_document("") {
+            Ok(condition) if filter.len() == 1 => condition.clone(),
+            _ => filter,
+        };
+        self.operator("$elemMatch", filter)
+    }
+}
+
+impl Field {
+    /// matches documents matching both this condition and the filter
+    pub fn and(self, other: impl Into<Filter>) -> Filter {
+        Filter::from(self).and(other)
+    }
+    /// matches documents matching either this condition or the filter
+    pub fn or(self, other: impl Into<Filter>) -> Filter {
+        Filter::from(self).or(other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::{Document, Regex, doc};
+
+    use super::{Filter, and, expr, field, nor, or, text};
+
+    #[test]
+    fn filters_produce_their_documents() {
+        let cases: Vec<(Filter, Document)> = vec![
+            (Filter::all(), doc! {}),
+            (field("a").eq(1).into(), doc! { "a": { "$eq": 1 } }),
+            (field("age").gte(18).lt(65).into(), doc! { "age": { "$gte": 18, "$lt": 65 } }),
+            (field("a").ne(1).gt(2).lte(3).into(), doc! { "a": { "$ne": 1, "$gt": 2, "$lte": 3 } }),
+            (field("tags").in_(["a", "b"]).into(), doc! { "tags": { "$in": ["a", "b"] } }),
+            (field("tags").nin(["a"]).all(["b", "c"]).size(2).into(), doc! { "tags": { "$nin": ["a"], "$all": ["b", "c"], "$size": 2_i64 } }),
+            (field("a").exists(false).into(), doc! { "a": { "$exists": false } }),
+            (field("a").type_("string").into(), doc! { "a": { "$type": "string" } }),
+            (field("name").regex("^a", "i").into(), doc! { "name": { "$regex": Regex { pattern: "^a".into(), options: "i".into() } } }),
+            (field("a").mod_(4, 1).into(), doc! { "a": { "$mod": [4_i64, 1_i64] } }),
+            // `!` negates the operators given so far
+            ((!field("a").gt(1).lt(5)).into(), doc! { "a": { "$not": { "$gt": 1, "$lt": 5 } } }),
+            ((!field("a").gt(1)).lt(0).into(), doc! { "a": { "$not": { "$gt": 1 }, "$lt": 0 } }),
+            (and([field("a").eq(1), field("b").eq(2)]), doc! { "$and": [{ "a": { "$eq": 1 } }, { "b": { "$eq": 2 } }] }),
+            (or([field("a").eq(1), field("b").eq(2)]), doc! { "$or": [{ "a": { "$eq": 1 } }, { "b": { "$eq": 2 } }] }),
+            (nor([field("a").eq(1)]), doc! { "$nor": [{ "a": { "$eq": 1 } }] }),
+            (and(Vec::<Filter>::new()), doc! { "$and": [] }),
+            // chained `and`/`or` append to the array instead of nesting it
+            (field("a").eq(1).and(field("b").eq(2)).and(field("c").eq(3)), doc! { "$and": [{ "a": { "$eq": 1 } }, { "b": { "$eq": 2 } }, { "c": { "$eq": 3 } }] }),
+            (field("a").eq(1).or(field("b").eq(2)).or(doc! { "c": 3 }), doc! { "$or": [{ "a": { "$eq": 1 } }, { "b": { "$eq": 2 } }, { "c": 3 }] }),
+            (field("a").eq(1).and(field("b").eq(2)).or(field("c").eq(3)), doc! { "$or": [{ "$and": [{ "a": { "$eq": 1 } }, { "b": { "$eq": 2 } }] }, { "c": { "$eq": 3 } }] }),
+            (Filter::from(doc! { "a": 1, "$and": [] }).and(field("b").eq(2)), doc! { "$and": [{ "a": 1, "$and": [] }, { "b": { "$eq": 2 } }] }),
+            // conditions on the elements are given by a field without name
+            (field("scores").elem_match(field("").gte(80).lt(85)).into(), doc! { "scores": { "$elemMatch": { "$gte": 80, "$lt": 85 } } }),
+            (field("items").elem_match(field("qty").gt(1).and(field("sku").eq("x"))).into(), doc! { "items": { "$elemMatch": { "$and": [{ "qty": { "$gt": 1 } }, { "sku": { "$eq": "x" } }] } } }),
+            (field("items").elem_match(doc! { "qty": 2 }).into(), doc! { "items": { "$elemMatch": { "qty": 2 } } }),
+            (expr(doc! { "$gt": ["$spent", "$budget"] }), doc! { "$expr": { "$gt": ["$spent", "$budget"] } }),
+            (text("coffee shop"), doc! { "$text": { "$search": "coffee shop" } }),
+        ];
+        for (i, (filter, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(filter.into_document(), expected, "case {}", i);
+        }
+    }
+
+    #[test]
+    fn filters_convert_into_documents() {
+        let filter = field("a").eq(1);
+        assert_eq!(Document::from(filter.clone()), doc! { "a": { "$eq": 1 } });
+        assert_eq!(Filter::from(filter).document(), &doc! { "a": { "$eq": 1 } });
+        assert_eq!(bson::Bson::from(text("x")), bson::Bson::Document(doc! { "$text": { "$search": "x" } }));
+    }
+}
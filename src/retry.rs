@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use builder_pattern::Builder;
+use http::{HeaderMap, StatusCode, header::RETRY_AFTER};
+
+use crate::Error;
+
+/// the status codes, which count as transient by default: 408, 429, 500, 502, 503 and 504
+pub const TRANSIENT_STATUSES: [StatusCode; 6] = [
+    StatusCode::REQUEST_TIMEOUT, StatusCode::TOO_MANY_REQUESTS, StatusCode::INTERNAL_SERVER_ERROR,
+    StatusCode::BAD_GATEWAY, StatusCode::SERVICE_UNAVAILABLE, StatusCode::GATEWAY_TIMEOUT,
+];
+
+#[derive(Builder, Debug, Clone)]
+/// When and how often failed requests are sent again
+///
+/// Transport errors and the transient status codes are retried with an exponential backoff.
+/// Requests which aren't idempotent (inserts, updates, deleteOne, replaceOne with upsert, function calls and GraphQL requests)
+/// are only retried, if `retry_non_idempotent` is set, because the first attempt may have been applied.
+pub struct RetryPolicy {
+    #[default(3)]
+    /// the maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    #[default(Duration::from_millis(200))]
+    /// the delay before the second attempt
+    pub initial_backoff: Duration,
+    #[default(Duration::from_secs(10))]
+    /// the upper bound of the delay
+    pub max_backoff: Duration,
+    #[default(2.0)]
+    /// the factor the delay grows by with every attempt
+    pub multiplier: f64,
+    #[default(true)]
+    /// randomizes the delay between half and all of the computed backoff
+    pub jitter: bool,
+    #[into]
+    #[default(TRANSIENT_STATUSES.to_vec())]
+    /// the status codes, which count as transient, [TRANSIENT_STATUSES] by default
+    pub transient_statuses: Vec<StatusCode>,
+    #[default(true)]
+    /// waits as long as the `Retry-After` header (in seconds) demands, if it's longer than the backoff
+    pub respect_retry_after: bool,
+    #[default(false)]
+    /// also retries non-idempotent requests like `insert_one` and `insert`
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new().build()
+    }
+}
+
+impl RetryPolicy {
+    /// sends every request exactly once
+    pub fn none() -> Self {
+        RetryPolicy::new().max_attempts(1).build()
+    }
+
+    /// true, if the status code counts as transient
+    pub fn is_transient(&self, status: StatusCode) -> bool {
+        self.transient_statuses.contains(&status)
+    }
+    /// true for transport errors and errors with a transient status code
+    pub fn is_transient_error(&self, error: &Error) -> bool {
+        match error {
+            Error::Transport(_) => true,
+            Error::Api { status, .. } => self.is_transient(*status),
+            _ => false,
+        }
+    }
+
+    /// the delay after the given (1-based) failed attempt
+    pub fn backoff(&self, attempt: u32, headers: Option<&HeaderMap>) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = if backoff.is_nan() { 0.0 } else { backoff };
+        let mut backoff = Duration::from_secs_f64(backoff.clamp(0.0, self.max_backoff.as_secs_f64()));
+
+        if self.jitter {
+            backoff = backoff.mul_f64(0.5 + random_fraction() / 2.0);
+        }
+        match headers.and_then(retry_after) {
+            Some(retry_after) if self.respect_retry_after => backoff.max(retry_after),
+            _ => backoff,
+        }
+    }
+}
+
+/// parses the `Retry-After` header given in seconds
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse::<u64>().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// a random number in [0, 1)
+fn random_fraction() -> f64 {
+    let mut bytes = [0u8; 8];
+    match getrandom::getrandom(&mut bytes) {
+        Ok(()) => (u64::from_le_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64,
+        Err(_) => 0.5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bson::doc;
+    use futures_executor::block_on;
+    use http::StatusCode;
+
+    use super::{RetryPolicy, TRANSIENT_STATUSES};
+    use crate::{Client, Collection, Error, Session, Update, scripted::{Scripted, retry_after}};
+
+    const FOUND: &str = r#"{"document":null}"#;
+
+    fn collection() -> Collection {
+        Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "c".into() }
+    }
+
+    fn client(transport: &std::sync::Arc<Scripted>, policy: RetryPolicy) -> Client {
+        let mut client = transport.client(Session::new("access", None));
+        client.retry_policy = policy;
+        client
+    }
+
+    fn no_jitter() -> RetryPolicy {
+        RetryPolicy::new().jitter(false).build()
+    }
+
+    #[test]
+    fn transient_statuses_are_retried() {
+        for status in TRANSIENT_STATUSES {
+            let transport = Scripted::new();
+            transport.respond(status.as_u16(), "").respond(200, FOUND);
+            block_on(client(&transport, no_jitter()).find_one(collection(), None, None)).unwrap();
+            assert_eq!(transport.requests().len(), 2, "{}", status);
+            assert_eq!(transport.sleeps(), vec![Duration::from_millis(200)], "{}", status);
+        }
+
+        let transport = Scripted::new();
+        transport.fail("connection reset").respond(200, FOUND);
+        block_on(client(&transport, no_jitter()).find_one(collection(), None, None)).unwrap();
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn client_errors_are_not_retried() {
+        for status in [400, 404, 409, 422] {
+            let transport = Scripted::new();
+            transport.respond(status, "").respond(200, FOUND);
+            let res = block_on(client(&transport, no_jitter()).find_one(collection(), None, None));
+            assert!(matches!(res, Err(Error::Api { status: x, .. }) if x.as_u16() == status), "{:?}", res);
+            assert_eq!(transport.requests().len(), 1);
+            assert!(transport.sleeps().is_empty());
+        }
+    }
+
+    #[test]
+    fn non_idempotent_actions_are_not_replayed() {
+        let transport = Scripted::new();
+        let client = client(&transport, no_jitter());
+        for _ in 0..5 {
+            transport.respond(503, "");
+        }
+        assert!(block_on(client.insert_one(collection(), doc! {"a": 1})).is_err());
+        assert!(block_on(client.insert(collection(), vec![doc! {"a": 1}])).is_err());
+        assert!(block_on(client.update_one(collection(), doc! {}, Update::new().set("a", 1), None)).is_err());
+        assert!(block_on(client.delete_one(collection(), doc! {})).is_err());
+        assert!(block_on(client.replace_one(collection(), doc! {}, doc! {"a": 1}, Some(true))).is_err());
+        assert_eq!(transport.requests().len(), 5);
+        assert!(transport.sleeps().is_empty());
+
+        transport.respond(503, "").respond(200, r#"{"matchedCount":1,"modifiedCount":1}"#);
+        block_on(client.replace_one(collection(), doc! {}, doc! {"a": 1}, None)).unwrap();
+        assert_eq!(transport.requests().len(), 7);
+    }
+
+    #[test]
+    fn non_idempotent_actions_are_replayed_if_allowed() {
+        let transport = Scripted::new();
+        transport.respond(503, "").respond(201, r#"{"insertedId":1}"#);
+        let client = client(&transport, RetryPolicy::new().jitter(false).retry_non_idempotent(true).build());
+        block_on(client.insert_one(collection(), doc! {"_id": 1})).unwrap();
+        assert_eq!(transport.requests().len(), 2);
+    }
+
+    #[test]
+    fn retry_after_overrides_a_shorter_backoff() {
+        let transport = Scripted::new();
+        transport.respond_with(429, retry_after(5), "").respond(200, FOUND);
+        block_on(client(&transport, no_jitter()).find_one(collection(), None, None)).unwrap();
+        assert_eq!(transport.sleeps(), vec![Duration::from_secs(5)]);
+
+        let transport = Scripted::new();
+        transport.respond_with(429, retry_after(5), "").respond(200, FOUND);
+        let policy = RetryPolicy::new().jitter(false).respect_retry_after(false).build();
+        block_on(client(&transport, policy).find_one(collection(), None, None)).unwrap();
+        assert_eq!(transport.sleeps(), vec![Duration::from_millis(200)]);
+    }
+
+    #[test]
+    fn attempts_are_capped() {
+        let transport = Scripted::new();
+        for _ in 0..10 {
+            transport.respond(503, "");
+        }
+        let policy = RetryPolicy::new().jitter(false).max_attempts(4).max_backoff(Duration::from_millis(500)).build();
+        let res = block_on(client(&transport, policy).find_one(collection(), None, None));
+        assert!(matches!(res, Err(Error::Api { status: StatusCode::SERVICE_UNAVAILABLE, .. })), "{:?}", res);
+        assert_eq!(transport.requests().len(), 4);
+        assert_eq!(transport.sleeps(), vec![Duration::from_millis(200), Duration::from_millis(400), Duration::from_millis(500)]);
+    }
+
+    #[test]
+    fn backoff_with_jitter_stays_within_half_and_all() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..6 {
+            let full = no_jitter().backoff(attempt, None);
+            let backoff = policy.backoff(attempt, None);
+            assert!(backoff >= full / 2 && backoff <= full, "{:?} {:?}", backoff, full);
+        }
+    }
+}
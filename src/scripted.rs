@@ -0,0 +1,87 @@
+//! A [Transport] answering with scripted responses, used by the unit tests
+use std::{collections::VecDeque, sync::{Arc, Mutex}, time::Duration};
+
+use http::{HeaderMap, HeaderValue, StatusCode};
+
+use crate::{Authentication, Client, Error, HttpRequest, HttpResponse, Session, Transport, transport::BoxFuture};
+
+#[derive(Debug, Default)]
+/// answers the requests in order with the scripted responses, `404` once they run out
+pub(crate) struct Scripted {
+    responses: Mutex<VecDeque<Result<HttpResponse, Error>>>,
+    requests: Mutex<Vec<HttpRequest>>,
+    sleeps: Mutex<Vec<Duration>>,
+}
+
+impl Scripted {
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+    /// adds a response with the status and body
+    pub(crate) fn respond(&self, status: u16, body: &str) -> &Self {
+        self.respond_with(status, HeaderMap::new(), body)
+    }
+    /// adds a response with the status, headers and body
+    pub(crate) fn respond_with(&self, status: u16, headers: HeaderMap, body: &str) -> &Self {
+        self.responses.lock().unwrap().push_back(Ok(HttpResponse {
+            status: StatusCode::from_u16(status).unwrap(),
+            headers,
+            body: body.as_bytes().to_vec(),
+        }));
+        self
+    }
+    /// adds a failure of the transport
+    pub(crate) fn fail(&self, error: &str) -> &Self {
+        self.responses.lock().unwrap().push_back(Err(Error::Transport(error.into())));
+        self
+    }
+    /// the requests sent so far
+    pub(crate) fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+    /// the durations waited for so far
+    pub(crate) fn sleeps(&self) -> Vec<Duration> {
+        self.sleeps.lock().unwrap().clone()
+    }
+    /// a client authenticated with the session, which sends its requests to this transport
+    pub(crate) fn client(self: &Arc<Self>, session: Session) -> Client {
+        Client::new()
+            .application_id("app")
+            .authentication(Authentication::Bearer(session))
+            .transport(self.clone() as Arc<dyn Transport>)
+            .build()
+    }
+}
+
+impl Transport for Scripted {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        self.requests.lock().unwrap().push(request);
+        let response = self.responses.lock().unwrap().pop_front().unwrap_or_else(|| Ok(HttpResponse {
+            status: StatusCode::NOT_FOUND,
+            headers: HeaderMap::new(),
+            body: b"unexpected request".to_vec(),
+        }));
+        Box::pin(async move { response })
+    }
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+        self.sleeps.lock().unwrap().push(duration);
+        Box::pin(async {})
+    }
+}
+
+/// the value of the header of the request as a string
+pub(crate) fn header<'a>(request: &'a HttpRequest, name: &str) -> Option<&'a str> {
+    request.headers.get(name).map(|x| x.to_str().unwrap())
+}
+
+/// the body of the request as json, `null` without body
+pub(crate) fn json_body(request: &HttpRequest) -> serde_json::Value {
+    serde_json::from_slice(request.body.as_deref().unwrap_or(b"null")).unwrap()
+}
+
+/// a `Retry-After` header of the seconds
+pub(crate) fn retry_after(seconds: u64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("retry-after", HeaderValue::from_str(&seconds.to_string()).unwrap());
+    headers
+}
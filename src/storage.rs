@@ -0,0 +1,265 @@
+use std::{collections::HashMap, fmt::Debug, sync::Mutex};
+
+use serde::{Serialize, Deserialize};
+
+use crate::{Error, Tokens};
+
+/// Persists the tokens of the logged in users of an [App](crate::App), so sessions survive restarts
+///
+/// The users are stored per application id, the list of user ids starts with the current user.
+pub trait SessionStorage: Debug + Send + Sync {
+    /// the stored tokens of the user
+    fn get(&self, app_id: &str, user_id: &str) -> Result<Option<Tokens>, Error>;
+    /// stores the tokens of the user
+    fn set(&self, app_id: &str, user_id: &str, tokens: &Tokens) -> Result<(), Error>;
+    /// removes the tokens of the user
+    fn remove(&self, app_id: &str, user_id: &str) -> Result<(), Error>;
+    /// the ids of the stored users, the current user first
+    fn user_ids(&self, app_id: &str) -> Result<Vec<String>, Error>;
+    /// replaces the ids of the stored users
+    fn set_user_ids(&self, app_id: &str, user_ids: &[String]) -> Result<(), Error>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// the stored users of an application
+struct AppSessions {
+    users: HashMap<String, Tokens>,
+    user_ids: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+/// Keeps the sessions in memory, they are lost when the process exits
+pub struct MemoryStorage {
+    apps: Mutex<HashMap<String, AppSessions>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage::default()
+    }
+}
+
+impl SessionStorage for MemoryStorage {
+    fn get(&self, app_id: &str, user_id: &str) -> Result<Option<Tokens>, Error> {
+        Ok(self.apps.lock().unwrap().get(app_id).and_then(|x| x.users.get(user_id).cloned()))
+    }
+    fn set(&self, app_id: &str, user_id: &str, tokens: &Tokens) -> Result<(), Error> {
+        self.apps.lock().unwrap().entry(app_id.to_string()).or_default().users.insert(user_id.to_string(), tokens.clone());
+        Ok(())
+    }
+    fn remove(&self, app_id: &str, user_id: &str) -> Result<(), Error> {
+        if let Some(app) = self.apps.lock().unwrap().get_mut(app_id) {
+            app.users.remove(user_id);
+        }
+        Ok(())
+    }
+    fn user_ids(&self, app_id: &str) -> Result<Vec<String>, Error> {
+        Ok(self.apps.lock().unwrap().get(app_id).map(|x| x.user_ids.clone()).unwrap_or_default())
+    }
+    fn set_user_ids(&self, app_id: &str, user_ids: &[String]) -> Result<(), Error> {
+        self.apps.lock().unwrap().entry(app_id.to_string()).or_default().user_ids = user_ids.to_vec();
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use file::FileStorage;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod file {
+    use std::{collections::HashMap, fs::OpenOptions, io::Write, path::PathBuf, sync::Mutex};
+
+    use super::{AppSessions, SessionStorage};
+    use crate::{Error, Tokens};
+
+    #[derive(Debug)]
+    /// Keeps the sessions in a json file, which is rewritten on every change
+    ///
+    /// The file contains the refresh tokens in plain text, so on unix it's created readable by the user only (mode `0600`).
+    /// Every change is written to a temporary file next to it, which then replaces the file, so it's never left half written.
+    pub struct FileStorage {
+        path: PathBuf,
+        lock: Mutex<()>,
+    }
+
+    impl FileStorage {
+        /// stores the sessions at `path`, the file is created on the first write
+        pub fn new(path: impl Into<PathBuf>) -> Self {
+            FileStorage { path: path.into(), lock: Mutex::new(()) }
+        }
+
+        fn read(&self) -> Result<HashMap<String, AppSessions>, Error> {
+            match std::fs::read(&self.path) {
+                Ok(x) => serde_json::from_slice(&x).map_err(|x| Error::Storage(format!("{:?}", x))),
+                Err(x) if x.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+                Err(x) => Err(Error::Storage(format!("{:?}", x))),
+            }
+        }
+        fn update(&self, app_id: &str, update: impl FnOnce(&mut AppSessions)) -> Result<(), Error> {
+            let _lock = self.lock.lock().unwrap();
+            let mut apps = self.read()?;
+            update(apps.entry(app_id.to_string()).or_default());
+            let body = serde_json::to_vec(&apps).map_err(|x| Error::Storage(format!("{:?}", x)))?;
+            self.write(&body).map_err(|x| Error::Storage(format!("{:?}", x)))
+        }
+        /// writes `<path>.tmp` and renames it to the path
+        fn write(&self, body: &[u8]) -> std::io::Result<()> {
+            let mut temp = self.path.clone().into_os_string();
+            temp.push(".tmp");
+            let temp = PathBuf::from(temp);
+
+            // a file left over by a crash may have other permissions
+            let _ = std::fs::remove_file(&temp);
+            let mut options = OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+            let result = options.open(&temp).and_then(|mut file| {
+                file.write_all(body)?;
+                file.sync_all()
+            }).and_then(|_| std::fs::rename(&temp, &self.path));
+            if result.is_err() {
+                let _ = std::fs::remove_file(&temp);
+            }
+            result
+        }
+        fn app(&self, app_id: &str) -> Result<AppSessions, Error> {
+            let _lock = self.lock.lock().unwrap();
+            Ok(self.read()?.remove(app_id).unwrap_or_default())
+        }
+    }
+
+    impl SessionStorage for FileStorage {
+        fn get(&self, app_id: &str, user_id: &str) -> Result<Option<Tokens>, Error> {
+            Ok(self.app(app_id)?.users.remove(user_id))
+        }
+        fn set(&self, app_id: &str, user_id: &str, tokens: &Tokens) -> Result<(), Error> {
+            self.update(app_id, |app| {
+                app.users.insert(user_id.to_string(), tokens.clone());
+            })
+        }
+        fn remove(&self, app_id: &str, user_id: &str) -> Result<(), Error> {
+            self.update(app_id, |app| {
+                app.users.remove(user_id);
+            })
+        }
+        fn user_ids(&self, app_id: &str) -> Result<Vec<String>, Error> {
+            Ok(self.app(app_id)?.user_ids)
+        }
+        fn set_user_ids(&self, app_id: &str, user_ids: &[String]) -> Result<(), Error> {
+            self.update(app_id, |app| app.user_ids = user_ids.to_vec())
+        }
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+pub use local::LocalStorage;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+mod local {
+    use super::SessionStorage;
+    use crate::{Error, Tokens};
+
+    #[derive(Debug, Default, Clone, Copy)]
+    /// Keeps the sessions in the `localStorage` of the browser, under the keys used by realm-web
+    pub struct LocalStorage;
+
+    impl LocalStorage {
+        pub fn new() -> Self {
+            LocalStorage
+        }
+
+        fn storage() -> Result<web_sys::Storage, Error> {
+            web_sys::window()
+                .ok_or_else(|| Error::Storage("No window".into()))?
+                .local_storage()
+                .map_err(|x| Error::Storage(format!("{:?}", x)))?
+                .ok_or_else(|| Error::Storage("No localStorage".into()))
+        }
+        fn get_item(key: &str) -> Result<Option<String>, Error> {
+            Self::storage()?.get_item(key).map_err(|x| Error::Storage(format!("{:?}", x)))
+        }
+        fn set_item(key: &str, value: Option<&str>) -> Result<(), Error> {
+            let storage = Self::storage()?;
+            match value {
+                Some(value) => storage.set_item(key, value),
+                None => storage.remove_item(key),
+            }.map_err(|x| Error::Storage(format!("{:?}", x)))
+        }
+    }
+
+    /// `realm-web:app(<app id>):<key>`
+    fn app_key(app_id: &str, key: &str) -> String {
+        format!("realm-web:app({}):{}", app_id, key)
+    }
+    /// `realm-web:app(<app id>):user(<user id>):<key>`
+    fn user_key(app_id: &str, user_id: &str, key: &str) -> String {
+        format!("realm-web:app({}):user({}):{}", app_id, user_id, key)
+    }
+
+    impl SessionStorage for LocalStorage {
+        fn get(&self, app_id: &str, user_id: &str) -> Result<Option<Tokens>, Error> {
+            let access_token = match Self::get_item(&user_key(app_id, user_id, "accessToken"))? {
+                Some(x) => x,
+                None => return Ok(None),
+            };
+            Ok(Some(Tokens {
+                access_token,
+                refresh_token: Self::get_item(&user_key(app_id, user_id, "refreshToken"))?,
+                user_id: Some(user_id.to_string()),
+                device_id: Self::get_item(&app_key(app_id, "deviceId"))?,
+            }))
+        }
+        fn set(&self, app_id: &str, user_id: &str, tokens: &Tokens) -> Result<(), Error> {
+            Self::set_item(&user_key(app_id, user_id, "accessToken"), Some(&tokens.access_token))?;
+            Self::set_item(&user_key(app_id, user_id, "refreshToken"), tokens.refresh_token.as_deref())?;
+            if let Some(device_id) = &tokens.device_id {
+                Self::set_item(&app_key(app_id, "deviceId"), Some(device_id))?;
+            }
+            Ok(())
+        }
+        fn remove(&self, app_id: &str, user_id: &str) -> Result<(), Error> {
+            Self::set_item(&user_key(app_id, user_id, "accessToken"), None)?;
+            Self::set_item(&user_key(app_id, user_id, "refreshToken"), None)
+        }
+        fn user_ids(&self, app_id: &str) -> Result<Vec<String>, Error> {
+            match Self::get_item(&app_key(app_id, "userIds"))? {
+                Some(x) => serde_json::from_str(&x).map_err(|x| Error::Storage(format!("{:?}", x))),
+                None => Ok(Vec::new()),
+            }
+        }
+        fn set_user_ids(&self, app_id: &str, user_ids: &[String]) -> Result<(), Error> {
+            let value = serde_json::to_string(user_ids).map_err(|x| Error::Storage(format!("{:?}", x)))?;
+            Self::set_item(&app_key(app_id, "userIds"), Some(&value))
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::{FileStorage, SessionStorage};
+    use crate::Tokens;
+
+    #[test]
+    fn file_storage_is_private_and_replaced() {
+        let dir = std::env::temp_dir().join(format!("realm-web-rs-storage-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sessions.json");
+        let _ = std::fs::remove_file(&path);
+
+        let storage = FileStorage::new(&path);
+        let tokens = Tokens { access_token: "access".into(), refresh_token: Some("refresh".into()), user_id: Some("user".into()), device_id: None };
+        storage.set("app", "user", &tokens).unwrap();
+        storage.set_user_ids("app", &["user".into()]).unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        assert!(!dir.join("sessions.json.tmp").exists());
+        assert_eq!(storage.get("app", "user").unwrap().and_then(|x| x.refresh_token), Some("refresh".into()));
+        assert_eq!(storage.user_ids("app").unwrap(), vec!["user".to_string()]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1471 @@
+//! An in-memory stand-in for the Data API, to exercise a [Client] without network access
+use std::{cmp::Ordering, collections::HashMap, sync::{Arc, Mutex}};
+
+use base64::Engine;
+
+use bson::{Bson, Document, doc, oid::ObjectId};
+use http::{HeaderMap, HeaderValue, StatusCode, header::CONTENT_TYPE};
+
+use crate::{Client, Collection, Authentication, Error, Transport, HttpRequest, HttpResponse, transport::BoxFuture, ejson};
+
+#[derive(Debug, Default)]
+/// A [Transport], which implements the Data API actions over an in-memory store
+///
+/// Supported are the comparison, logical, element, array and `$regex` query operators (except `$where`, `$expr`, `$text`, ...),
+/// inclusion and exclusion projections, sort/skip/limit, the field and array update operators, updates with simple pipelines
+/// and the `$match`, `$sort`, `$skip`, `$limit`, `$project`, `$addFields`, `$unset`, `$unwind`, `$group` and `$count` aggregation stages.
+/// Like the Data API, it answers `application/json` requests with plain json and `application/ejson` requests with canonical extended json.
+/// The authentication of the requests isn't checked.
+pub struct MockDataApi {
+    collections: Mutex<HashMap<(String, String, String), Vec<Document>>>,
+}
+
+#[allow(unused)]
+impl MockDataApi {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// creates a [Client], which sends its requests to this mock
+    pub fn client(self: &Arc<Self>) -> Client {
+        Client::new()
+            .application_id("mock")
+            .authentication(Authentication::ApiKey("mock".into()))
+            .transport(self.clone() as Arc<dyn Transport>)
+            .build()
+    }
+    /// inserts documents without going through the api, e.g. as test fixtures
+    pub fn insert_documents(&self, collection: &Collection, documents: impl IntoIterator<Item = Document>) {
+        let mut collections = self.collections.lock().unwrap();
+        let stored = collections.entry(key(collection)).or_default();
+        stored.extend(documents.into_iter().map(with_id));
+    }
+    /// all documents of the collection, in insertion order
+    pub fn documents(&self, collection: &Collection) -> Vec<Document> {
+        self.collections.lock().unwrap().get(&key(collection)).cloned().unwrap_or_default()
+    }
+    /// removes all documents of all collections
+    pub fn clear(&self) {
+        self.collections.lock().unwrap().clear();
+    }
+
+    fn handle(&self, request: &HttpRequest) -> Result<(StatusCode, Document), MockError> {
+        let path = request.url.split('?').next().unwrap_or_default();
+        let action = path.rsplit_once("/action/")
+            .map(|(_, action)| action)
+            .ok_or_else(|| MockError::not_found(format!("no data api action: {}", path)))?;
+
+        let body = parse_body(request)?;
+        let collection = (get_str(&body, "dataSource")?, get_str(&body, "database")?, get_str(&body, "collection")?);
+        let mut collections = self.collections.lock().unwrap();
+        let documents = collections.entry(collection).or_default();
+
+        match action {
+            "findOne" => {
+                let filter = get_doc_or_default(&body, "filter")?;
+                let found = match find_first(documents, &filter)? {
+                    Some(document) => match get_doc_opt(&body, "projection")? {
+                        Some(projection) => Bson::Document(project(&document, &projection)?),
+                        None => Bson::Document(document),
+                    },
+                    None => Bson::Null,
+                };
+                Ok((StatusCode::OK, doc! {"document": found}))
+            },
+            "find" => {
+                let filter = get_doc_or_default(&body, "filter")?;
+                let mut found = filter_documents(documents, &filter)?;
+                if let Some(sort) = get_doc_opt(&body, "sort")? {
+                    sort_documents(&mut found, &sort);
+                }
+                let skip = get_i64_opt(&body, "skip")?.unwrap_or(0).max(0) as usize;
+                let limit = get_i64_opt(&body, "limit")?.filter(|x| *x > 0).map(|x| x as usize).unwrap_or(usize::MAX);
+                let projection = get_doc_opt(&body, "projection")?;
+                let found = found.into_iter().skip(skip).take(limit)
+                    .map(|x| match &projection {
+                        Some(projection) => project(&x, projection).map(Bson::Document),
+                        None => Ok(Bson::Document(x)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((StatusCode::OK, doc! {"documents": found}))
+            },
+            "insertOne" => {
+                let document = get_doc(&body, "document")?;
+                let id = insert_document(documents, document)?;
+                Ok((StatusCode::CREATED, doc! {"insertedId": id}))
+            },
+            "insertMany" => {
+                let inserted = get_array(&body, "documents")?.iter()
+                    .map(|x| match x {
+                        Bson::Document(x) => insert_document(documents, x.clone()),
+                        _ => Err(MockError::invalid("documents must be an array of documents")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((StatusCode::CREATED, doc! {"insertedIds": inserted}))
+            },
+            "updateOne" | "updateMany" => {
+                let filter = get_doc(&body, "filter")?;
+                let update = body.get("update").cloned().ok_or_else(|| MockError::invalid("update is required"))?;
+                let upsert = body.get_bool("upsert").unwrap_or(false);
+                validate_update(&update)?;
+
+                let mut matched = 0;
+                let mut modified = 0;
+                for document in documents.iter_mut() {
+                    if !matches(document, &filter)? {
+                        continue;
+                    }
+                    matched += 1;
+                    let before = document.clone();
+                    apply_update(document, &update, false)?;
+                    if *document != before {
+                        modified += 1;
+                    }
+                    if action == "updateOne" {
+                        break;
+                    }
+                }
+
+                let mut res = doc! {"matchedCount": matched, "modifiedCount": modified};
+                if matched == 0 && upsert {
+                    let mut document = equality_fields(&filter);
+                    apply_update(&mut document, &update, true)?;
+                    res.insert("upsertedId", insert_document(documents, document)?);
+                }
+                Ok((StatusCode::OK, res))
+            },
+            "replaceOne" => {
+                let filter = get_doc(&body, "filter")?;
+                let mut replacement = get_doc(&body, "replacement")?;
+                let upsert = body.get_bool("upsert").unwrap_or(false);
+                if replacement.keys().any(|x| x.starts_with('$')) {
+                    return Err(MockError::invalid("replacement document must not contain update operators"));
+                }
+
+                let mut res = doc! {"matchedCount": 0, "modifiedCount": 0};
+                let position = position_of(documents, &filter)?;
+                match position {
+                    Some(position) => {
+                        let document = &mut documents[position];
+                        if let Some(id) = document.get("_id") {
+                            replacement.insert("_id", id.clone());
+                        }
+                        let modified = *document != replacement;
+                        *document = replacement;
+                        res = doc! {"matchedCount": 1, "modifiedCount": if modified {1} else {0}};
+                    },
+                    None if upsert => {
+                        if let (false, Some(id)) = (replacement.contains_key("_id"), equality_fields(&filter).get("_id")) {
+                            replacement.insert("_id", id.clone());
+                        }
+                        res.insert("upsertedId", insert_document(documents, replacement)?);
+                    },
+                    None => {},
+                }
+                Ok((StatusCode::OK, res))
+            },
+            "deleteOne" => {
+                let filter = get_doc(&body, "filter")?;
+                let deleted = match position_of(documents, &filter)? {
+                    Some(position) => {
+                        documents.remove(position);
+                        1
+                    },
+                    None => 0,
+                };
+                Ok((StatusCode::OK, doc! {"deletedCount": deleted}))
+            },
+            "deleteMany" => {
+                let filter = get_doc(&body, "filter")?;
+                let before = documents.len();
+                let mut kept = Vec::with_capacity(before);
+                for document in documents.drain(..) {
+                    if !matches(&document, &filter)? {
+                        kept.push(document);
+                    }
+                }
+                let deleted = (before - kept.len()) as i32;
+                *documents = kept;
+                Ok((StatusCode::OK, doc! {"deletedCount": deleted}))
+            },
+            "aggregate" => {
+                let pipeline = get_array(&body, "pipeline")?;
+                let output = aggregate(documents.clone(), pipeline)?;
+                Ok((StatusCode::OK, doc! {"documents": output.into_iter().map(Bson::Document).collect::<Vec<_>>()}))
+            },
+            x => Err(MockError::not_found(format!("unknown action: {}", x))),
+        }
+    }
+}
+
+impl Transport for MockDataApi {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        let (status, body) = match self.handle(&request) {
+            Ok(x) => x,
+            Err(x) => (x.status, doc! {"error": x.error, "error_code": x.error_code, "link": "https://www.mongodb.com/docs/atlas/app-services/data-api/"}),
+        };
+        let ejson = request.headers.get("accept")
+            .and_then(|x| x.to_str().ok())
+            .map(|x| x.contains("application/ejson"))
+            .unwrap_or(false);
+        let (content_type, body) = match ejson {
+            true => ("application/ejson", Bson::Document(body).into_canonical_extjson()),
+            false => ("application/json", into_plain_json(Bson::Document(body))),
+        };
+        let mut headers = HeaderMap::new();
+        headers.append(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        Box::pin(std::future::ready(Ok(HttpResponse { status, headers, body: body.to_string().into_bytes() })))
+    }
+}
+
+/// converts into the plain json, which the Data API answers `application/json` requests with
+///
+/// Object ids, decimals and binaries (base64) become strings, dates ISO-8601 strings and all numbers json numbers.
+/// Types without a json counterpart, like min and max keys, become null.
+fn into_plain_json(value: Bson) -> serde_json::Value {
+    use serde_json::Value;
+    match value {
+        Bson::Double(x) => serde_json::Number::from_f64(x).map(Value::Number).unwrap_or_else(|| Value::String(x.to_string())),
+        Bson::Int32(x) => x.into(),
+        Bson::Int64(x) => x.into(),
+        Bson::String(x) | Bson::Symbol(x) => Value::String(x),
+        Bson::Boolean(x) => Value::Bool(x),
+        Bson::ObjectId(x) => Value::String(x.to_hex()),
+        Bson::DateTime(x) => x.try_to_rfc3339_string().map(Value::String).unwrap_or_else(|_| x.timestamp_millis().into()),
+        Bson::Decimal128(x) => Value::String(x.to_string()),
+        Bson::Binary(x) => Value::String(base64::engine::general_purpose::STANDARD.encode(x.bytes)),
+        Bson::RegularExpression(x) => Value::String(x.pattern),
+        Bson::Timestamp(x) => x.time.into(),
+        Bson::JavaScriptCode(x) => Value::String(x),
+        Bson::JavaScriptCodeWithScope(x) => Value::String(x.code),
+        Bson::Array(x) => Value::Array(x.into_iter().map(into_plain_json).collect()),
+        Bson::Document(x) => Value::Object(x.into_iter().map(|(k, v)| (k, into_plain_json(v))).collect()),
+        Bson::Null | Bson::Undefined | Bson::MinKey | Bson::MaxKey | Bson::DbPointer(_) => Value::Null,
+    }
+}
+
+#[derive(Debug)]
+struct MockError {
+    status: StatusCode,
+    error: String,
+    error_code: &'static str,
+}
+
+impl MockError {
+    fn invalid(error: impl Into<String>) -> Self {
+        MockError { status: StatusCode::BAD_REQUEST, error: error.into(), error_code: "InvalidParameter" }
+    }
+    fn not_found(error: impl Into<String>) -> Self {
+        MockError { status: StatusCode::NOT_FOUND, error: error.into(), error_code: "NotFound" }
+    }
+}
+
+fn key(collection: &Collection) -> (String, String, String) {
+    (collection.data_source.clone(), collection.database.clone(), collection.collection.clone())
+}
+
+/// parses the json or extended json body
+fn parse_body(request: &HttpRequest) -> Result<Document, MockError> {
+    let body = request.body.as_deref().unwrap_or_default();
+    let value: serde_json::Value = serde_json::from_slice(body).map_err(|x| MockError::invalid(format!("invalid json: {}", x)))?;
+    match ejson::from_extjson(value) {
+        Ok(Bson::Document(x)) => Ok(x),
+        Ok(_) => Err(MockError::invalid("request body must be an object")),
+        Err(x) => Err(MockError::invalid(x.to_string())),
+    }
+}
+
+fn get_str(body: &Document, key: &str) -> Result<String, MockError> {
+    body.get_str(key).map(|x| x.to_string()).map_err(|_| MockError::invalid(format!("missing {}", key)))
+}
+fn get_doc(body: &Document, key: &str) -> Result<Document, MockError> {
+    get_doc_opt(body, key)?.ok_or_else(|| MockError::invalid(format!("missing {}", key)))
+}
+fn get_doc_or_default(body: &Document, key: &str) -> Result<Document, MockError> {
+    Ok(get_doc_opt(body, key)?.unwrap_or_default())
+}
+fn get_doc_opt(body: &Document, key: &str) -> Result<Option<Document>, MockError> {
+    match body.get(key) {
+        None | Some(Bson::Null) => Ok(None),
+        Some(Bson::Document(x)) => Ok(Some(x.clone())),
+        Some(_) => Err(MockError::invalid(format!("{} must be a document", key))),
+    }
+}
+fn get_array<'a>(body: &'a Document, key: &str) -> Result<&'a Vec<Bson>, MockError> {
+    body.get_array(key).map_err(|_| MockError::invalid(format!("{} must be an array", key)))
+}
+fn get_i64_opt(body: &Document, key: &str) -> Result<Option<i64>, MockError> {
+    match body.get(key) {
+        None | Some(Bson::Null) => Ok(None),
+        Some(x) => as_f64(x).map(|x| Some(x as i64)).ok_or_else(|| MockError::invalid(format!("{} must be a number", key))),
+    }
+}
+
+fn insert_document(documents: &mut Vec<Document>, document: Document) -> Result<Bson, MockError> {
+    let document = with_id(document);
+    let id = document.get("_id").cloned().unwrap_or(Bson::Null);
+    if documents.iter().any(|x| x.get("_id").map(|x| values_equal(x, &id)).unwrap_or(false)) {
+        return Err(MockError::invalid(format!("Duplicate key error: _id: {}", id)));
+    }
+    documents.push(document);
+    Ok(id)
+}
+
+/// prepends a generated `_id`, if the document doesn't have one
+fn with_id(document: Document) -> Document {
+    if document.contains_key("_id") {
+        return document;
+    }
+    let mut with_id = doc! {"_id": ObjectId::new()};
+    with_id.extend(document);
+    with_id
+}
+
+fn find_first(documents: &[Document], filter: &Document) -> Result<Option<Document>, MockError> {
+    Ok(position_of(documents, filter)?.map(|x| documents[x].clone()))
+}
+fn position_of(documents: &[Document], filter: &Document) -> Result<Option<usize>, MockError> {
+    for (i, document) in documents.iter().enumerate() {
+        if matches(document, filter)? {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+fn filter_documents(documents: &[Document], filter: &Document) -> Result<Vec<Document>, MockError> {
+    let mut found = Vec::new();
+    for document in documents {
+        if matches(document, filter)? {
+            found.push(document.clone());
+        }
+    }
+    Ok(found)
+}
+
+/// the top level equality conditions of a filter, which are the base of an upserted document
+fn equality_fields(filter: &Document) -> Document {
+    let mut document = Document::new();
+    for (key, value) in filter {
+        if key.starts_with('$') || is_operator_document(value) {
+            continue;
+        }
+        set_path(&mut document, key, value.clone());
+    }
+    document
+}
+
+// ---- query filters ----
+
+/// true, if the document matches the filter
+fn matches(document: &Document, filter: &Document) -> Result<bool, MockError> {
+    for (key, condition) in filter {
+        let matched = match key.as_str() {
+            "$and" => {
+                let mut all = true;
+                for filter in sub_filters(condition)? {
+                    all &= matches(document, filter)?;
+                }
+                all
+            },
+            "$or" => {
+                let mut any = false;
+                for filter in sub_filters(condition)? {
+                    any |= matches(document, filter)?;
+                }
+                any
+            },
+            "$nor" => {
+                let mut any = false;
+                for filter in sub_filters(condition)? {
+                    any |= matches(document, filter)?;
+                }
+                !any
+            },
+            "$comment" => true,
+            x if x.starts_with('$') => return Err(MockError::invalid(format!("unsupported query operator: {}", x))),
+            path => matches_value(get_path(document, path), condition)?,
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// true, if the string value (or a string element of the array value) matches the pattern with the options `i`, `m`, `s` and `x`
+fn regex_matches(value: Option<&Bson>, pattern: &str, options: &str) -> Result<bool, MockError> {
+    if let Some(x) = options.chars().find(|x| !"imsx".contains(*x)) {
+        return Err(MockError::invalid(format!("unsupported regex option: {}", x)));
+    }
+    let pattern = if options.is_empty() { pattern.to_string() } else { format!("(?{}){}", options, pattern) };
+    let regex = regex_lite::Regex::new(&pattern).map_err(|x| MockError::invalid(format!("invalid regular expression: {}", x)))?;
+    Ok(match value {
+        Some(Bson::String(x)) => regex.is_match(x),
+        Some(Bson::Array(x)) => x.iter().any(|x| matches!(x, Bson::String(x) if regex.is_match(x))),
+        _ => false,
+    })
+}
+
+fn sub_filters(condition: &Bson) -> Result<Vec<&Document>, MockError> {
+    match condition {
+        Bson::Array(x) => x.iter()
+            .map(|x| x.as_document().ok_or_else(|| MockError::invalid("logical operators take an array of documents")))
+            .collect(),
+        _ => Err(MockError::invalid("logical operators take an array of documents")),
+    }
+}
+
+fn is_operator_document(value: &Bson) -> bool {
+    match value {
+        Bson::Document(x) => x.keys().next().map(|x| x.starts_with('$')).unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// true, if the (possibly missing) field value matches the condition
+fn matches_value(value: Option<&Bson>, condition: &Bson) -> Result<bool, MockError> {
+    let operators = match condition {
+        Bson::Document(x) if is_operator_document(condition) => x,
+        Bson::RegularExpression(x) => return regex_matches(value, &x.pattern, &x.options),
+        _ => return Ok(equals(value, condition)),
+    };
+
+    for (operator, argument) in operators {
+        let matched = match operator.as_str() {
+            "$eq" => equals(value, argument),
+            "$ne" => !equals(value, argument),
+            "$gt" => compares(value, argument, |x| x == Ordering::Greater),
+            "$gte" => compares(value, argument, |x| x != Ordering::Less),
+            "$lt" => compares(value, argument, |x| x == Ordering::Less),
+            "$lte" => compares(value, argument, |x| x != Ordering::Greater),
+            "$in" => in_array(value, argument)?,
+            "$nin" => !in_array(value, argument)?,
+            "$exists" => value.is_some() == is_truthy(argument),
+            "$not" => !matches_value(value, argument)?,
+            "$size" => match (value, as_f64(argument)) {
+                (Some(Bson::Array(x)), Some(size)) => x.len() as f64 == size,
+                _ => false,
+            },
+            "$all" => match (value, argument) {
+                (Some(Bson::Array(x)), Bson::Array(all)) => all.iter().all(|a| x.iter().any(|x| values_equal(x, a))),
+                _ => false,
+            },
+            "$elemMatch" => match (value, argument) {
+                (Some(Bson::Array(x)), Bson::Document(filter)) => {
+                    let mut any = false;
+                    for element in x {
+                        any |= match element {
+                            Bson::Document(element) if !is_operator_document(argument) => matches(element, filter)?,
+                            element => matches_value(Some(element), argument)?,
+                        };
+                    }
+                    any
+                },
+                _ => false,
+            },
+            "$type" => match value {
+                Some(x) => type_matches(x, argument),
+                None => false,
+            },
+            "$regex" => match argument {
+                Bson::RegularExpression(x) => regex_matches(value, &x.pattern, operators.get_str("$options").unwrap_or(&x.options))?,
+                Bson::String(x) => regex_matches(value, x, operators.get_str("$options").unwrap_or(""))?,
+                _ => return Err(MockError::invalid("$regex takes a regular expression or a string")),
+            },
+            "$options" if operators.contains_key("$regex") => true,
+            x => return Err(MockError::invalid(format!("unsupported query operator: {}", x))),
+        };
+        if !matched {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// equality as the server implements it: arrays match, if one of their elements is equal
+fn equals(value: Option<&Bson>, target: &Bson) -> bool {
+    match value {
+        None => matches!(target, Bson::Null),
+        Some(Bson::Array(x)) if !matches!(target, Bson::Array(_)) => x.iter().any(|x| values_equal(x, target)),
+        Some(x) => values_equal(x, target),
+    }
+}
+
+fn compares(value: Option<&Bson>, target: &Bson, accept: impl Fn(Ordering) -> bool) -> bool {
+    match value {
+        None => false,
+        Some(Bson::Array(x)) => x.iter().any(|x| compare_values(x, target).map(&accept).unwrap_or(false)),
+        Some(x) => compare_values(x, target).map(accept).unwrap_or(false),
+    }
+}
+
+fn in_array(value: Option<&Bson>, argument: &Bson) -> Result<bool, MockError> {
+    match argument {
+        Bson::Array(x) => Ok(x.iter().any(|x| equals(value, x))),
+        _ => Err(MockError::invalid("$in and $nin take an array")),
+    }
+}
+
+fn type_matches(value: &Bson, argument: &Bson) -> bool {
+    let name = match value {
+        Bson::Double(_) => "double",
+        Bson::String(_) => "string",
+        Bson::Document(_) => "object",
+        Bson::Array(_) => "array",
+        Bson::Binary(_) => "binData",
+        Bson::ObjectId(_) => "objectId",
+        Bson::Boolean(_) => "bool",
+        Bson::DateTime(_) => "date",
+        Bson::Null => "null",
+        Bson::RegularExpression(_) => "regex",
+        Bson::Int32(_) => "int",
+        Bson::Timestamp(_) => "timestamp",
+        Bson::Int64(_) => "long",
+        Bson::Decimal128(_) => "decimal",
+        _ => "",
+    };
+    match argument {
+        Bson::String(x) => x == name || (x == "number" && as_f64(value).is_some()),
+        Bson::Array(x) => x.iter().any(|x| type_matches(value, x)),
+        x => as_f64(x).map(|x| x as i32 == value.element_type() as i32).unwrap_or(false),
+    }
+}
+
+// ---- comparison ----
+
+fn as_f64(value: &Bson) -> Option<f64> {
+    match value {
+        Bson::Int32(x) => Some(*x as f64),
+        Bson::Int64(x) => Some(*x as f64),
+        Bson::Double(x) => Some(*x),
+        _ => None,
+    }
+}
+
+fn is_truthy(value: &Bson) -> bool {
+    match value {
+        Bson::Boolean(x) => *x,
+        Bson::Null | Bson::Undefined => false,
+        x => as_f64(x).map(|x| x != 0.0).unwrap_or(true),
+    }
+}
+
+fn values_equal(a: &Bson, b: &Bson) -> bool {
+    match (a, b) {
+        (Bson::Array(a), Bson::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b)),
+        (Bson::Document(a), Bson::Document(b)) => a.len() == b.len() && a.iter().zip(b).all(|((ka, a), (kb, b))| ka == kb && values_equal(a, b)),
+        _ => compare_values(a, b) == Some(Ordering::Equal),
+    }
+}
+
+/// compares values of the same type, numbers are compared across their types
+fn compare_values(a: &Bson, b: &Bson) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (as_f64(a), as_f64(b)) {
+        return a.partial_cmp(&b);
+    }
+    match (a, b) {
+        (Bson::String(a), Bson::String(b)) => Some(a.cmp(b)),
+        (Bson::ObjectId(a), Bson::ObjectId(b)) => Some(a.cmp(b)),
+        (Bson::DateTime(a), Bson::DateTime(b)) => Some(a.cmp(b)),
+        (Bson::Boolean(a), Bson::Boolean(b)) => Some(a.cmp(b)),
+        (Bson::Timestamp(a), Bson::Timestamp(b)) => Some((a.time, a.increment).cmp(&(b.time, b.increment))),
+        (Bson::Null, Bson::Null) | (Bson::MinKey, Bson::MinKey) | (Bson::MaxKey, Bson::MaxKey) => Some(Ordering::Equal),
+        (a, b) if a == b => Some(Ordering::Equal),
+        _ => None,
+    }
+}
+
+/// the position of the type in the bson sort order
+fn type_rank(value: Option<&Bson>) -> u8 {
+    match value {
+        Some(Bson::MinKey) => 0,
+        None | Some(Bson::Null) | Some(Bson::Undefined) => 1,
+        Some(Bson::Int32(_)) | Some(Bson::Int64(_)) | Some(Bson::Double(_)) | Some(Bson::Decimal128(_)) => 2,
+        Some(Bson::String(_)) | Some(Bson::Symbol(_)) => 3,
+        Some(Bson::Document(_)) => 4,
+        Some(Bson::Array(_)) => 5,
+        Some(Bson::Binary(_)) => 6,
+        Some(Bson::ObjectId(_)) => 7,
+        Some(Bson::Boolean(_)) => 8,
+        Some(Bson::DateTime(_)) => 9,
+        Some(Bson::Timestamp(_)) => 10,
+        Some(Bson::RegularExpression(_)) => 11,
+        Some(Bson::MaxKey) => 13,
+        Some(_) => 12,
+    }
+}
+
+fn sort_documents(documents: &mut [Document], sort: &Document) {
+    documents.sort_by(|a, b| {
+        for (path, direction) in sort {
+            let (x, y) = (get_path(a, path), get_path(b, path));
+            let ordering = type_rank(x).cmp(&type_rank(y))
+                .then_with(|| match (x, y) {
+                    (Some(x), Some(y)) => compare_values(x, y).unwrap_or(Ordering::Equal),
+                    _ => Ordering::Equal,
+                });
+            let ordering = match as_f64(direction) {
+                Some(x) if x < 0.0 => ordering.reverse(),
+                _ => ordering,
+            };
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+        }
+        Ordering::Equal
+    });
+}
+
+// ---- paths ----
+
+/// gets the value at a dotted path, array elements are addressed by their index
+fn get_path<'a>(document: &'a Document, path: &str) -> Option<&'a Bson> {
+    let mut parts = path.split('.');
+    let mut value = document.get(parts.next()?)?;
+    for part in parts {
+        value = match value {
+            Bson::Document(x) => x.get(part)?,
+            Bson::Array(x) => x.get(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// sets the value at a dotted path, missing documents on the way are created
+fn set_path(document: &mut Document, path: &str, value: Bson) -> bool {
+    match path.split_once('.') {
+        None => {
+            document.insert(path, value);
+            true
+        },
+        Some((head, rest)) => {
+            let child = document.entry(head.to_string()).or_insert_with(|| Bson::Document(Document::new()));
+            match child {
+                Bson::Document(x) => set_path(x, rest, value),
+                _ => false,
+            }
+        },
+    }
+}
+
+fn remove_path(document: &mut Document, path: &str) -> Option<Bson> {
+    match path.split_once('.') {
+        None => document.remove(path),
+        Some((head, rest)) => match document.get_mut(head) {
+            Some(Bson::Document(x)) => remove_path(x, rest),
+            _ => None,
+        },
+    }
+}
+
+fn get_path_mut<'a>(document: &'a mut Document, path: &str) -> Option<&'a mut Bson> {
+    match path.split_once('.') {
+        None => document.get_mut(path),
+        Some((head, rest)) => match document.get_mut(head) {
+            Some(Bson::Document(x)) => get_path_mut(x, rest),
+            _ => None,
+        },
+    }
+}
+
+// ---- projections ----
+
+fn project(document: &Document, projection: &Document) -> Result<Document, MockError> {
+    let include = projection.iter().any(|(k, v)| k != "_id" && is_truthy(v));
+    let exclude_id = projection.get("_id").map(|x| !is_truthy(x)).unwrap_or(false);
+
+    let mut projected = match include {
+        true => {
+            let mut projected = Document::new();
+            if !exclude_id {
+                if let Some(id) = document.get("_id") {
+                    projected.insert("_id", id.clone());
+                }
+            }
+            for (path, value) in projection {
+                if path == "_id" {
+                    continue;
+                }
+                if !is_truthy(value) {
+                    return Err(MockError::invalid("cannot mix inclusion and exclusion in a projection"));
+                }
+                if let Some(x) = get_path(document, path) {
+                    set_path(&mut projected, path, x.clone());
+                }
+            }
+            projected
+        },
+        false => {
+            let mut projected = document.clone();
+            for path in projection.keys() {
+                remove_path(&mut projected, path);
+            }
+            projected
+        },
+    };
+    if exclude_id {
+        projected.remove("_id");
+    }
+    Ok(projected)
+}
+
+// ---- updates ----
+
+fn validate_update(update: &Bson) -> Result<(), MockError> {
+    match update {
+        Bson::Document(x) if !x.is_empty() && x.keys().all(|x| x.starts_with('$')) => Ok(()),
+        Bson::Document(_) => Err(MockError::invalid("update document requires atomic operators")),
+        Bson::Array(x) if !x.is_empty() => Ok(()),
+        _ => Err(MockError::invalid("update must be a document or a pipeline")),
+    }
+}
+
+fn apply_update(document: &mut Document, update: &Bson, is_insert: bool) -> Result<(), MockError> {
+    match update {
+        Bson::Array(pipeline) => apply_pipeline_update(document, pipeline),
+        Bson::Document(x) => apply_operators(document, x, is_insert),
+        _ => Err(MockError::invalid("update must be a document or a pipeline")),
+    }
+}
+
+/// applies the `$set`/`$addFields`, `$unset`, `$project` and `$replaceRoot`/`$replaceWith` stages of an update pipeline
+fn apply_pipeline_update(document: &mut Document, pipeline: &[Bson]) -> Result<(), MockError> {
+    let id = document.get("_id").cloned();
+    for stage in pipeline {
+        let stage = stage.as_document().ok_or_else(|| MockError::invalid("a pipeline stage must be a document"))?;
+        let (name, argument) = match (stage.len(), stage.iter().next()) {
+            (1, Some(x)) => x,
+            _ => return Err(MockError::invalid("a pipeline stage must have exactly one field")),
+        };
+        match (name.as_str(), argument) {
+            ("$set" | "$addFields", Bson::Document(fields)) => add_fields(document, fields),
+            ("$unset", x) => unset_fields(document, x),
+            ("$project", Bson::Document(projection)) => *document = project(document, projection)?,
+            ("$replaceRoot", Bson::Document(x)) => match x.get("newRoot").map(|x| evaluate(document, x)) {
+                Some(Bson::Document(root)) => *document = root,
+                _ => return Err(MockError::invalid("newRoot must evaluate to a document")),
+            },
+            ("$replaceWith", x) => match evaluate(document, x) {
+                Bson::Document(root) => *document = root,
+                _ => return Err(MockError::invalid("$replaceWith must evaluate to a document")),
+            },
+            (x, _) => return Err(MockError::invalid(format!("unsupported update pipeline stage: {}", x))),
+        }
+    }
+    match (id, document.get("_id")) {
+        (Some(id), Some(x)) if !values_equal(&id, x) => Err(MockError::invalid("the _id field is immutable")),
+        (Some(id), None) => {
+            let mut with_id = doc! {"_id": id};
+            with_id.extend(std::mem::take(document));
+            *document = with_id;
+            Ok(())
+        },
+        _ => Ok(()),
+    }
+}
+
+fn add_fields(document: &mut Document, fields: &Document) {
+    for (path, expression) in fields {
+        let value = evaluate(document, expression);
+        set_path(document, path, value);
+    }
+}
+
+fn unset_fields(document: &mut Document, paths: &Bson) {
+    let paths = match paths {
+        Bson::Array(x) => x.iter().filter_map(|x| x.as_str()).collect(),
+        x => x.as_str().into_iter().collect::<Vec<_>>(),
+    };
+    for path in paths {
+        remove_path(document, path);
+    }
+}
+
+/// evaluates field paths (`"$field"`) and `$literal` in an aggregation expression, other operators aren't supported
+fn evaluate(document: &Document, expression: &Bson) -> Bson {
+    match expression {
+        Bson::String(x) if x.starts_with('$') && !x.starts_with("$$") => get_path(document, &x[1..]).cloned().unwrap_or(Bson::Null),
+        Bson::Document(x) => match x.get("$literal") {
+            Some(literal) if x.len() == 1 => literal.clone(),
+            _ => Bson::Document(x.iter().map(|(k, v)| (k.clone(), evaluate(document, v))).collect()),
+        },
+        Bson::Array(x) => Bson::Array(x.iter().map(|x| evaluate(document, x)).collect()),
+        x => x.clone(),
+    }
+}
+
+fn apply_operators(document: &mut Document, update: &Document, is_insert: bool) -> Result<(), MockError> {
+    for (operator, fields) in update {
+        let fields = fields.as_document().ok_or_else(|| MockError::invalid(format!("{} takes a document", operator)))?;
+        for (path, argument) in fields {
+            if path == "_id" && operator != "$setOnInsert" && document.get("_id").map(|x| !values_equal(x, argument)).unwrap_or(false) {
+                return Err(MockError::invalid("the _id field is immutable"));
+            }
+            match operator.as_str() {
+                "$set" => {
+                    set_path(document, path, argument.clone());
+                },
+                "$setOnInsert" => if is_insert {
+                    set_path(document, path, argument.clone());
+                },
+                "$unset" => {
+                    remove_path(document, path);
+                },
+                "$inc" | "$mul" => {
+                    let current = get_path(document, path).cloned();
+                    let value = match (operator == "$inc", current) {
+                        (true, None) => argument.clone(),
+                        (true, Some(x)) => arithmetic(&x, argument, |a, b| a + b, |a, b| a.checked_add(b))?,
+                        (false, x) => arithmetic(&x.unwrap_or(Bson::Int32(0)), argument, |a, b| a * b, |a, b| a.checked_mul(b))?,
+                    };
+                    set_path(document, path, value);
+                },
+                "$min" | "$max" => {
+                    let replace = match get_path(document, path) {
+                        None => true,
+                        Some(x) => {
+                            let ordering = type_rank(Some(argument)).cmp(&type_rank(Some(x)))
+                                .then_with(|| compare_values(argument, x).unwrap_or(Ordering::Equal));
+                            (operator == "$min" && ordering == Ordering::Less) || (operator == "$max" && ordering == Ordering::Greater)
+                        },
+                    };
+                    if replace {
+                        set_path(document, path, argument.clone());
+                    }
+                },
+                "$rename" => {
+                    let target = argument.as_str().ok_or_else(|| MockError::invalid("$rename takes field names"))?;
+                    if let Some(x) = remove_path(document, path) {
+                        set_path(document, target, x);
+                    }
+                },
+                "$currentDate" => {
+                    set_path(document, path, Bson::DateTime(bson::DateTime::now()));
+                },
+                "$push" | "$addToSet" => {
+                    let (values, modifiers) = match argument {
+                        Bson::Document(x) if x.contains_key("$each") => (x.get_array("$each").map_err(|_| MockError::invalid("$each takes an array"))?.clone(), Some(x)),
+                        x => (vec![x.clone()], None),
+                    };
+                    let array = array_at(document, path)?;
+                    for value in values {
+                        if operator == "$push" || !array.iter().any(|x| values_equal(x, &value)) {
+                            array.push(value);
+                        }
+                    }
+                    if let Some(modifiers) = modifiers {
+                        if let Some(Bson::Document(sort)) = modifiers.get("$sort") {
+                            let mut documents = array.iter().filter_map(|x| x.as_document().cloned()).collect::<Vec<_>>();
+                            sort_documents(&mut documents, sort);
+                            *array = documents.into_iter().map(Bson::Document).collect();
+                        } else if let Some(direction) = modifiers.get("$sort").and_then(as_f64) {
+                            array.sort_by(|a, b| {
+                                let ordering = type_rank(Some(a)).cmp(&type_rank(Some(b))).then_with(|| compare_values(a, b).unwrap_or(Ordering::Equal));
+                                if direction < 0.0 { ordering.reverse() } else { ordering }
+                            });
+                        }
+                        if let Some(slice) = modifiers.get("$slice").and_then(as_f64) {
+                            let slice = slice as i64;
+                            if slice >= 0 {
+                                array.truncate(slice as usize);
+                            } else {
+                                let keep = (-slice) as usize;
+                                if array.len() > keep {
+                                    array.drain(..array.len() - keep);
+                                }
+                            }
+                        }
+                    }
+                },
+                "$pull" => {
+                    if let Some(Bson::Array(array)) = get_path_mut(document, path) {
+                        let mut kept = Vec::with_capacity(array.len());
+                        for element in array.drain(..) {
+                            let remove = match (&element, argument) {
+                                (Bson::Document(element), Bson::Document(filter)) if !is_operator_document(argument) => matches(element, filter)?,
+                                (element, argument) => matches_value(Some(element), argument)?,
+                            };
+                            if !remove {
+                                kept.push(element);
+                            }
+                        }
+                        *array = kept;
+                    }
+                },
+                "$pop" => {
+                    if let Some(Bson::Array(array)) = get_path_mut(document, path) {
+                        if as_f64(argument).map(|x| x < 0.0).unwrap_or(false) {
+                            if !array.is_empty() {
+                                array.remove(0);
+                            }
+                        } else {
+                            array.pop();
+                        }
+                    }
+                },
+                x => return Err(MockError::invalid(format!("unsupported update operator: {}", x))),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// the array at the path, which gets created if it's missing
+fn array_at<'a>(document: &'a mut Document, path: &str) -> Result<&'a mut Vec<Bson>, MockError> {
+    if get_path(document, path).is_none() {
+        set_path(document, path, Bson::Array(Vec::new()));
+    }
+    match get_path_mut(document, path) {
+        Some(Bson::Array(x)) => Ok(x),
+        _ => Err(MockError::invalid(format!("{} is not an array", path))),
+    }
+}
+
+fn arithmetic(a: &Bson, b: &Bson, float: impl Fn(f64, f64) -> f64, int: impl Fn(i64, i64) -> Option<i64>) -> Result<Bson, MockError> {
+    Ok(match (a, b) {
+        (Bson::Int32(a), Bson::Int32(b)) => match int(*a as i64, *b as i64) {
+            Some(x) if i32::try_from(x).is_ok() => Bson::Int32(x as i32),
+            Some(x) => Bson::Int64(x),
+            None => Bson::Double(float(*a as f64, *b as f64)),
+        },
+        (Bson::Int32(_) | Bson::Int64(_), Bson::Int32(_) | Bson::Int64(_)) => {
+            let (a, b) = (as_f64(a).unwrap() as i64, as_f64(b).unwrap() as i64);
+            match int(a, b) {
+                Some(x) => Bson::Int64(x),
+                None => Bson::Double(float(a as f64, b as f64)),
+            }
+        },
+        (a, b) => match (as_f64(a), as_f64(b)) {
+            (Some(a), Some(b)) => Bson::Double(float(a, b)),
+            _ => return Err(MockError::invalid("cannot apply arithmetic to a non-numeric value")),
+        },
+    })
+}
+
+// ---- aggregation ----
+
+fn aggregate(mut documents: Vec<Document>, pipeline: &[Bson]) -> Result<Vec<Document>, MockError> {
+    for stage in pipeline {
+        let stage = stage.as_document().ok_or_else(|| MockError::invalid("a pipeline stage must be a document"))?;
+        let (name, argument) = match (stage.len(), stage.iter().next()) {
+            (1, Some(x)) => x,
+            _ => return Err(MockError::invalid("a pipeline stage must have exactly one field")),
+        };
+        documents = match (name.as_str(), argument) {
+            ("$match", Bson::Document(filter)) => filter_documents(&documents, filter)?,
+            ("$sort", Bson::Document(sort)) => {
+                sort_documents(&mut documents, sort);
+                documents
+            },
+            ("$skip", x) => documents.into_iter().skip(as_f64(x).unwrap_or(0.0) as usize).collect(),
+            ("$limit", x) => documents.into_iter().take(as_f64(x).unwrap_or(0.0) as usize).collect(),
+            ("$project", Bson::Document(projection)) => documents.iter().map(|x| project(x, projection)).collect::<Result<_, _>>()?,
+            ("$addFields" | "$set", Bson::Document(fields)) => documents.into_iter().map(|mut x| {
+                add_fields(&mut x, fields);
+                x
+            }).collect(),
+            ("$unset", x) => documents.into_iter().map(|mut document| {
+                unset_fields(&mut document, x);
+                document
+            }).collect(),
+            ("$unwind", x) => unwind(documents, x)?,
+            ("$group", Bson::Document(group)) => group_documents(&documents, group)?,
+            ("$count", Bson::String(field)) => match documents.len() {
+                0 => Vec::new(),
+                x => vec![doc! {field: x as i32}],
+            },
+            (x, _) => return Err(MockError::invalid(format!("unsupported pipeline stage: {}", x))),
+        };
+    }
+    Ok(documents)
+}
+
+fn unwind(documents: Vec<Document>, argument: &Bson) -> Result<Vec<Document>, MockError> {
+    let (path, index, preserve) = match argument {
+        Bson::String(x) => (x.as_str(), None, false),
+        Bson::Document(x) => (
+            x.get_str("path").map_err(|_| MockError::invalid("$unwind requires a path"))?,
+            x.get_str("includeArrayIndex").ok(),
+            x.get_bool("preserveNullAndEmptyArrays").unwrap_or(false),
+        ),
+        _ => return Err(MockError::invalid("$unwind takes a path or a document")),
+    };
+    let path = path.strip_prefix('$').ok_or_else(|| MockError::invalid("the $unwind path must start with $"))?;
+
+    let mut unwound = Vec::new();
+    for document in documents {
+        match get_path(&document, path).cloned() {
+            Some(Bson::Array(elements)) if !elements.is_empty() => for (i, element) in elements.into_iter().enumerate() {
+                let mut document = document.clone();
+                set_path(&mut document, path, element);
+                if let Some(index) = index {
+                    set_path(&mut document, index, Bson::Int64(i as i64));
+                }
+                unwound.push(document);
+            },
+            Some(Bson::Array(_)) | Some(Bson::Null) | None if !preserve => {},
+            _ => {
+                let mut document = document;
+                if let Some(index) = index {
+                    set_path(&mut document, index, Bson::Null);
+                }
+                unwound.push(document);
+            },
+        }
+    }
+    Ok(unwound)
+}
+
+/// groups by the `_id` expression, supports the `$sum`, `$avg`, `$min`, `$max`, `$first`, `$last`, `$push` and `$addToSet` accumulators
+fn group_documents(documents: &[Document], group: &Document) -> Result<Vec<Document>, MockError> {
+    let id = group.get("_id").ok_or_else(|| MockError::invalid("$group requires an _id"))?;
+    let mut groups: Vec<(Bson, Vec<&Document>)> = Vec::new();
+    for document in documents {
+        let key = evaluate(document, id);
+        match groups.iter_mut().find(|(x, _)| values_equal(x, &key)) {
+            Some((_, members)) => members.push(document),
+            None => groups.push((key, vec![document])),
+        }
+    }
+
+    let mut output = Vec::new();
+    for (key, members) in groups {
+        let mut result = doc! {"_id": key};
+        for (field, accumulator) in group.iter().filter(|(x, _)| *x != "_id") {
+            let (operator, expression) = match accumulator.as_document().filter(|x| x.len() == 1).and_then(|x| x.iter().next()) {
+                Some(x) => x,
+                None => return Err(MockError::invalid(format!("the accumulator of {} must have exactly one operator", field))),
+            };
+            let values = members.iter().map(|x| evaluate(x, expression)).collect::<Vec<_>>();
+            let value = match operator.as_str() {
+                "$sum" => values.iter().filter(|x| as_f64(x).is_some()).try_fold(Bson::Int32(0), |sum, x| arithmetic(&sum, x, |a, b| a + b, |a, b| a.checked_add(b)))?,
+                "$avg" => {
+                    let numbers = values.iter().filter_map(as_f64).collect::<Vec<_>>();
+                    match numbers.len() {
+                        0 => Bson::Null,
+                        x => Bson::Double(numbers.iter().sum::<f64>() / x as f64),
+                    }
+                },
+                "$min" | "$max" => values.into_iter()
+                    .filter(|x| !matches!(x, Bson::Null))
+                    .reduce(|a, b| match (compare_values(&a, &b), operator == "$min") {
+                        (Some(Ordering::Greater), true) | (Some(Ordering::Less), false) => b,
+                        _ => a,
+                    })
+                    .unwrap_or(Bson::Null),
+                "$first" => values.into_iter().next().unwrap_or(Bson::Null),
+                "$last" => values.into_iter().last().unwrap_or(Bson::Null),
+                "$push" => Bson::Array(values),
+                "$addToSet" => {
+                    let mut set: Vec<Bson> = Vec::new();
+                    for value in values {
+                        if !set.iter().any(|x| values_equal(x, &value)) {
+                            set.push(value);
+                        }
+                    }
+                    Bson::Array(set)
+                },
+                x => return Err(MockError::invalid(format!("unsupported accumulator: {}", x))),
+            };
+            result.insert(field, value);
+        }
+        output.push(result);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bson::{Bson, Document, Regex, doc, oid::ObjectId};
+    use futures_executor::block_on;
+
+    use super::MockDataApi;
+    use crate::{Client, Collection, Error, Update, WireFormat, query::field, update::Push};
+
+    fn collection() -> Collection {
+        Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "people".into() }
+    }
+
+    /// a mock with the documents and a client, which keeps all bson types
+    fn setup(documents: Vec<Document>) -> (Arc<MockDataApi>, Client) {
+        let mock = Arc::new(MockDataApi::new());
+        mock.insert_documents(&collection(), documents);
+        let mut client = mock.client();
+        client.wire_format = WireFormat::CanonicalEjson;
+        (mock, client)
+    }
+
+    fn people() -> Vec<Document> {
+        vec![
+            doc! {"_id": 1, "name": "ada", "age": 36, "tags": ["math", "code"], "address": {"city": "london"}},
+            doc! {"_id": 2, "name": "grace", "age": 85, "tags": ["code", "navy"], "address": {"city": "new york"}},
+            doc! {"_id": 3, "name": "alan", "age": 41, "tags": [], "score": 2.5},
+            doc! {"_id": 4, "name": "edsger", "age": 72, "scores": [{"subject": "math", "points": 9}, {"subject": "art", "points": 3}]},
+        ]
+    }
+
+    fn find(client: &Client, filter: Document) -> Vec<i32> {
+        let res = block_on(client.find(collection(), Some(filter), None, Some(doc! {"_id": 1}), None, None)).unwrap();
+        res.documents.unwrap().iter().map(|x| x.get_i32("_id").unwrap()).collect()
+    }
+
+    fn stored(mock: &MockDataApi, id: i32) -> Document {
+        mock.documents(&collection()).into_iter().find(|x| x.get_i32("_id") == Ok(id)).unwrap()
+    }
+
+    fn update(mock: &MockDataApi, client: &Client, id: i32, update: Update) -> Document {
+        block_on(client.update_one(collection(), doc! {"_id": id}, update, None)).unwrap();
+        stored(mock, id)
+    }
+
+    #[test]
+    fn comparison_operators() {
+        let (_, client) = setup(people());
+        assert_eq!(find(&client, doc! {"name": "ada"}), [1]);
+        assert_eq!(find(&client, doc! {"age": {"$eq": 85}}), [2]);
+        assert_eq!(find(&client, doc! {"age": {"$ne": 85}}), [1, 3, 4]);
+        assert_eq!(find(&client, doc! {"age": {"$gt": 41}}), [2, 4]);
+        assert_eq!(find(&client, doc! {"age": {"$gte": 41}}), [2, 3, 4]);
+        assert_eq!(find(&client, doc! {"age": {"$lt": 41}}), [1]);
+        assert_eq!(find(&client, doc! {"age": {"$lte": 41}}), [1, 3]);
+        assert_eq!(find(&client, doc! {"age": {"$gt": 40, "$lt": 80}}), [3, 4]);
+        assert_eq!(find(&client, doc! {"age": {"$in": [36, 72]}}), [1, 4]);
+        assert_eq!(find(&client, doc! {"age": {"$nin": [36, 72]}}), [2, 3]);
+        assert_eq!(find(&client, doc! {"score": {"$gt": 2}}), [3]);
+        assert_eq!(find(&client, doc! {"age": {"$gt": 40.5_f64}}), [2, 3, 4]);
+    }
+
+    #[test]
+    fn logical_and_element_operators() {
+        let (_, client) = setup(people());
+        assert_eq!(find(&client, doc! {"$and": [{"age": {"$gt": 40}}, {"age": {"$lt": 80}}]}), [3, 4]);
+        assert_eq!(find(&client, doc! {"$or": [{"name": "ada"}, {"age": 85}]}), [1, 2]);
+        assert_eq!(find(&client, doc! {"$nor": [{"name": "ada"}, {"age": 85}]}), [3, 4]);
+        assert_eq!(find(&client, doc! {"age": {"$not": {"$gt": 41}}}), [1, 3]);
+        assert_eq!(find(&client, doc! {"score": {"$exists": true}}), [3]);
+        assert_eq!(find(&client, doc! {"score": {"$exists": false}}), [1, 2, 4]);
+        assert_eq!(find(&client, doc! {"score": null}), [1, 2, 4]);
+        assert_eq!(find(&client, doc! {"score": {"$type": "double"}}), [3]);
+        assert_eq!(find(&client, doc! {"age": {"$type": "number"}}), [1, 2, 3, 4]);
+        assert_eq!(find(&client, doc! {"name": {"$type": 2}}), [1, 2, 3, 4]);
+        assert_eq!(find(&client, doc! {"address.city": "london"}), [1]);
+        assert_eq!(find(&client, doc! {"$comment": "all", "age": {"$gt": 0}}), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn regex_operators() {
+        let (_, client) = setup(people());
+        assert_eq!(find(&client, doc! {"name": {"$regex": "^a"}}), [1, 3]);
+        assert_eq!(find(&client, doc! {"name": {"$regex": "^A", "$options": "i"}}), [1, 3]);
+        assert_eq!(find(&client, doc! {"name": Regex { pattern: "r$".into(), options: "".into() }}), [4]);
+        assert_eq!(find(&client, doc! {"tags": {"$regex": "^na"}}), [2]);
+        assert_eq!(find(&client, field("name").regex("^A", "i").into()), [1, 3]);
+        assert_eq!(find(&client, (!field("name").regex("^a", "")).into()), [2, 4]);
+
+        let res = block_on(client.find(collection(), Some(doc! {"name": {"$regex": "("}}), None, None, None, None));
+        assert!(matches!(res, Err(Error::Api { status, .. }) if status == 400), "{:?}", res);
+    }
+
+    #[test]
+    fn array_operators() {
+        let (_, client) = setup(people());
+        assert_eq!(find(&client, doc! {"tags": "code"}), [1, 2]);
+        assert_eq!(find(&client, doc! {"tags": ["math", "code"]}), [1]);
+        assert_eq!(find(&client, doc! {"tags": {"$all": ["code", "navy"]}}), [2]);
+        assert_eq!(find(&client, doc! {"tags": {"$size": 0}}), [3]);
+        assert_eq!(find(&client, doc! {"scores": {"$elemMatch": {"subject": "math", "points": {"$gt": 5}}}}), [4]);
+        assert_eq!(find(&client, doc! {"scores": {"$elemMatch": {"subject": "art", "points": {"$gt": 5}}}}), Vec::<i32>::new());
+        assert_eq!(find(&client, doc! {"scores.1.points": 3}), [4]);
+    }
+
+    #[test]
+    fn unsupported_operators_are_rejected() {
+        let (_, client) = setup(people());
+        let res = block_on(client.find(collection(), Some(doc! {"$where": "true"}), None, None, None, None));
+        assert!(matches!(res, Err(Error::Api { status, .. }) if status == 400), "{:?}", res);
+        let res = block_on(client.find(collection(), Some(doc! {"age": {"$near": 1}}), None, None, None, None));
+        assert!(matches!(res, Err(Error::Api { status, .. }) if status == 400), "{:?}", res);
+    }
+
+    #[test]
+    fn find_one_and_projections() {
+        let (_, client) = setup(people());
+        let found = block_on(client.find_one(collection(), Some(doc! {"name": "ada"}), Some(doc! {"name": 1, "address.city": 1}))).unwrap();
+        assert_eq!(found.document, Some(doc! {"_id": 1, "name": "ada", "address": {"city": "london"}}));
+
+        let found = block_on(client.find_one(collection(), Some(doc! {"name": "ada"}), Some(doc! {"name": 1, "_id": 0}))).unwrap();
+        assert_eq!(found.document, Some(doc! {"name": "ada"}));
+
+        let found = block_on(client.find_one(collection(), Some(doc! {"_id": 3}), Some(doc! {"tags": 0, "score": 0}))).unwrap();
+        assert_eq!(found.document, Some(doc! {"_id": 3, "name": "alan", "age": 41}));
+
+        let found = block_on(client.find_one(collection(), Some(doc! {"name": "nobody"}), None)).unwrap();
+        assert_eq!(found.document, None);
+
+        let res = block_on(client.find_one(collection(), None, Some(doc! {"name": 1, "age": 0})));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn sort_skip_and_limit() {
+        let (_, client) = setup(people());
+        let ids = |sort: Document, limit: Option<i32>, skip: Option<i32>| -> Vec<i32> {
+            let res = block_on(client.find(collection(), None, None, Some(sort), limit, skip)).unwrap();
+            res.documents.unwrap().iter().map(|x| x.get_i32("_id").unwrap()).collect()
+        };
+        assert_eq!(ids(doc! {"age": 1}, None, None), [1, 3, 4, 2]);
+        assert_eq!(ids(doc! {"age": -1}, None, None), [2, 4, 3, 1]);
+        assert_eq!(ids(doc! {"name": 1}, Some(2), None), [1, 3]);
+        assert_eq!(ids(doc! {"name": 1}, Some(2), Some(1)), [3, 4]);
+        assert_eq!(ids(doc! {"name": 1}, None, Some(3)), [2]);
+        // missing fields sort before numbers
+        assert_eq!(ids(doc! {"score": -1, "_id": 1}, None, None), [3, 1, 2, 4]);
+    }
+
+    #[test]
+    fn inserts() {
+        let (mock, client) = setup(Vec::new());
+        let id = ObjectId::new();
+        let res = block_on(client.insert_one(collection(), doc! {"_id": id, "name": "ada"})).unwrap();
+        assert_eq!(res.inserted_id, Some(Bson::ObjectId(id)));
+
+        let res = block_on(client.insert(collection(), vec![doc! {"name": "grace"}, doc! {"name": "alan"}])).unwrap();
+        assert_eq!(res.inserted_ids.as_ref().map(|x| x.len()), Some(2));
+        let documents = mock.documents(&collection());
+        assert_eq!(documents.len(), 3);
+        assert_eq!(documents[1].get("_id"), res.inserted_ids.unwrap().first());
+
+        let res = block_on(client.insert_one(collection(), doc! {"_id": id}));
+        assert!(matches!(res, Err(Error::Api { status, .. }) if status == 400), "{:?}", res);
+    }
+
+    #[test]
+    fn ids_of_any_type() {
+        for format in [WireFormat::Json, WireFormat::CanonicalEjson] {
+            let mock = Arc::new(MockDataApi::new());
+            let mut client = mock.client();
+            client.wire_format = format;
+
+            let res = block_on(client.insert_one(collection(), doc! {"_id": 5, "name": "ada"})).unwrap();
+            assert_eq!(res.inserted_id, Some(Bson::Int32(5)));
+            let res = block_on(client.insert(collection(), vec![doc! {"_id": "abc"}, doc! {"_id": 6_i64}])).unwrap();
+            assert_eq!(res.inserted_ids.unwrap()[0], Bson::from("abc"));
+
+            let res = block_on(client.update_one(collection(), doc! {"_id": 9}, Update::new().set("name", "grace"), Some(true))).unwrap();
+            assert_eq!(res.upserted_id, Some(Bson::Int32(9)));
+            let res = block_on(client.replace_one(collection(), doc! {"_id": "xyz"}, doc! {"name": "alan"}, Some(true))).unwrap();
+            assert_eq!(res.upserted_id, Some(Bson::from("xyz")));
+            assert_eq!(mock.documents(&collection()).len(), 5);
+        }
+    }
+
+    #[test]
+    fn field_update_operators() {
+        let (mock, client) = setup(people());
+        assert_eq!(update(&mock, &client, 1, Update::new().set("age", 37).set("address.zip", "nw1")),
+            doc! {"_id": 1, "name": "ada", "age": 37, "tags": ["math", "code"], "address": {"city": "london", "zip": "nw1"}});
+        assert_eq!(update(&mock, &client, 1, Update::new().unset("tags").unset("address.zip")),
+            doc! {"_id": 1, "name": "ada", "age": 37, "address": {"city": "london"}});
+        assert_eq!(update(&mock, &client, 1, Update::new().inc("age", 3).inc("visits", 1)).get("age"), Some(&Bson::Int32(40)));
+        assert_eq!(stored(&mock, 1).get("visits"), Some(&Bson::Int32(1)));
+        assert_eq!(update(&mock, &client, 1, Update::new().inc("age", 0.5)).get("age"), Some(&Bson::Double(40.5)));
+        assert_eq!(update(&mock, &client, 3, Update::new().mul("score", 2).mul("missing", 5)),
+            doc! {"_id": 3, "name": "alan", "age": 41, "tags": [], "score": 5.0, "missing": 0});
+        assert_eq!(update(&mock, &client, 2, Update::new().inc("age", i32::MAX)).get("age"), Some(&Bson::Int64(85 + i32::MAX as i64)));
+        assert_eq!(update(&mock, &client, 4, Update::new().min("age", 70).max("high", 1)).get("age"), Some(&Bson::Int32(70)));
+        assert_eq!(update(&mock, &client, 4, Update::new().min("age", 90).max("age", 60)).get("age"), Some(&Bson::Int32(70)));
+        assert_eq!(stored(&mock, 4).get("high"), Some(&Bson::Int32(1)));
+        assert_eq!(update(&mock, &client, 3, Update::new().rename("score", "rating")).get("rating"), Some(&Bson::Double(5.0)));
+        assert!(!stored(&mock, 3).contains_key("score"));
+        assert!(matches!(update(&mock, &client, 3, Update::new().current_date("seen")).get("seen"), Some(Bson::DateTime(_))));
+    }
+
+    #[test]
+    fn array_update_operators() {
+        let (mock, client) = setup(people());
+        let tags = |document: Document| document.get_array("tags").unwrap().clone();
+        assert_eq!(tags(update(&mock, &client, 1, Update::new().push("tags", "logic"))), vec![Bson::from("math"), "code".into(), "logic".into()]);
+        assert_eq!(tags(update(&mock, &client, 1, Update::new().add_to_set("tags", "math"))).len(), 3);
+        assert_eq!(tags(update(&mock, &client, 1, Update::new().add_to_set_each("tags", ["math", "poetry"]))).len(), 4);
+        assert_eq!(tags(update(&mock, &client, 1, Update::new().pull("tags", "math"))), vec![Bson::from("code"), "logic".into(), "poetry".into()]);
+        assert_eq!(tags(update(&mock, &client, 1, Update::new().pull("tags", doc! {"$in": ["code", "logic"]}))), vec![Bson::from("poetry")]);
+        assert_eq!(tags(update(&mock, &client, 2, Update::new().pop_first("tags"))), vec![Bson::from("navy")]);
+        assert_eq!(tags(update(&mock, &client, 2, Update::new().pop_last("tags"))), Vec::<Bson>::new());
+        assert_eq!(tags(update(&mock, &client, 3, Update::new().push_each("tags", Push::each([3, 1, 2]).sort(1).slice(2)))), vec![Bson::Int32(1), Bson::Int32(2)]);
+        assert_eq!(tags(update(&mock, &client, 3, Update::new().push_each("tags", Push::each([5]).slice(-2)))), vec![Bson::Int32(2), Bson::Int32(5)]);
+
+        let scores = update(&mock, &client, 4, Update::new().push_each("scores", Push::each([doc! {"subject": "law", "points": 5}]).sort(doc! {"points": -1})));
+        let points = scores.get_array("scores").unwrap().iter().map(|x| x.as_document().unwrap().get_i32("points").unwrap()).collect::<Vec<_>>();
+        assert_eq!(points, [9, 5, 3]);
+        let scores = update(&mock, &client, 4, Update::new().pull("scores", doc! {"points": {"$lt": 5}}));
+        assert_eq!(scores.get_array("scores").unwrap().len(), 2);
+        assert_eq!(update(&mock, &client, 3, Update::new().push("new.list", 1)).get_document("new").unwrap(), &doc! {"list": [1]});
+    }
+
+    #[test]
+    fn update_counts_and_errors() {
+        let (mock, client) = setup(people());
+        let res = block_on(client.update(collection(), doc! {"age": {"$gt": 40}}, Update::new().set("senior", true), None)).unwrap();
+        assert_eq!((res.matched_count, res.modified_count, res.upserted_id), (3, 3, None));
+        let res = block_on(client.update(collection(), doc! {"age": {"$gt": 40}}, Update::new().set("senior", true), None)).unwrap();
+        assert_eq!((res.matched_count, res.modified_count), (3, 0));
+        let res = block_on(client.update_one(collection(), doc! {"age": {"$gt": 40}}, Update::new().set("first", true), None)).unwrap();
+        assert_eq!((res.matched_count, res.modified_count), (1, 1));
+        assert_eq!(mock.documents(&collection()).iter().filter(|x| x.contains_key("first")).count(), 1);
+
+        let res = block_on(client.update_one(collection(), doc! {"_id": 1}, Update::new().set("_id", 5), None));
+        assert!(matches!(res, Err(Error::Api { status, .. }) if status == 400), "{:?}", res);
+    }
+
+    #[test]
+    fn upserts() {
+        let (mock, client) = setup(Vec::new());
+        let res = block_on(client.update_one(collection(), doc! {"name": "ada", "age": {"$gt": 30}},
+            Update::new().set("age", 36).set_on_insert("created", true), Some(true))).unwrap();
+        assert_eq!((res.matched_count, res.modified_count), (0, 0));
+        let id = res.upserted_id.unwrap();
+        assert_eq!(mock.documents(&collection()), [doc! {"_id": id.clone(), "name": "ada", "age": 36, "created": true}]);
+
+        let res = block_on(client.update_one(collection(), doc! {"name": "ada"}, Update::new().set("age", 37).set_on_insert("created", false), Some(true))).unwrap();
+        assert_eq!((res.matched_count, res.modified_count, res.upserted_id), (1, 1, None));
+        assert_eq!(mock.documents(&collection()), [doc! {"_id": id, "name": "ada", "age": 37, "created": true}]);
+
+        let id = ObjectId::new();
+        let res = block_on(client.replace_one(collection(), doc! {"_id": id}, doc! {"name": "grace"}, Some(true))).unwrap();
+        assert_eq!(res.upserted_id, Some(Bson::ObjectId(id)));
+        assert_eq!(mock.documents(&collection())[1], doc! {"name": "grace", "_id": id});
+        let res = block_on(client.replace_one(collection(), doc! {"name": "alan"}, doc! {"name": "alan"}, None)).unwrap();
+        assert_eq!((res.matched_count, res.upserted_id), (0, None));
+        assert_eq!(mock.documents(&collection()).len(), 2);
+    }
+
+    #[test]
+    fn replace_one() {
+        let (mock, client) = setup(people());
+        let res = block_on(client.replace_one(collection(), doc! {"name": "alan"}, doc! {"name": "alan turing"}, None)).unwrap();
+        assert_eq!((res.matched_count, res.modified_count), (1, 1));
+        assert_eq!(stored(&mock, 3), doc! {"name": "alan turing", "_id": 3});
+
+        let res = block_on(client.replace_one(collection(), doc! {"_id": 3}, doc! {"$set": {"name": "x"}}, None));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn pipeline_updates() {
+        let (mock, client) = setup(people());
+        let pipeline = vec![
+            doc! {"$set": {"city": "$address.city", "label": {"$literal": "$name"}}},
+            doc! {"$unset": ["address", "tags"]},
+        ];
+        assert_eq!(update(&mock, &client, 1, Update::pipeline(pipeline)), doc! {"_id": 1, "name": "ada", "age": 36, "city": "london", "label": "$name"});
+        assert_eq!(update(&mock, &client, 2, Update::pipeline(vec![doc! {"$replaceWith": "$address"}])), doc! {"_id": 2, "city": "new york"});
+        assert_eq!(update(&mock, &client, 3, Update::pipeline(vec![doc! {"$project": {"name": 1}}])), doc! {"_id": 3, "name": "alan"});
+        assert_eq!(update(&mock, &client, 4, Update::pipeline(vec![doc! {"$replaceRoot": {"newRoot": {"n": "$name"}}}])), doc! {"_id": 4, "n": "edsger"});
+    }
+
+    #[test]
+    fn deletes() {
+        let (mock, client) = setup(people());
+        assert_eq!(block_on(client.delete_one(collection(), doc! {"age": {"$gt": 40}})).unwrap().deleted_count, 1);
+        assert_eq!(find(&client, doc! {}), [1, 3, 4]);
+        assert_eq!(block_on(client.delete(collection(), doc! {"age": {"$gt": 40}})).unwrap().deleted_count, 2);
+        assert_eq!(block_on(client.delete(collection(), doc! {"age": {"$gt": 40}})).unwrap().deleted_count, 0);
+        assert_eq!(mock.documents(&collection()).len(), 1);
+    }
+
+    fn aggregate(client: &Client, pipeline: Vec<Document>) -> Vec<Document> {
+        block_on(client.aggregate(collection(), pipeline)).unwrap().documents
+    }
+
+    #[test]
+    fn aggregation_stages() {
+        let (_, client) = setup(people());
+        assert_eq!(aggregate(&client, vec![
+            doc! {"$match": {"age": {"$gt": 40}}},
+            doc! {"$sort": {"age": -1}},
+            doc! {"$skip": 1},
+            doc! {"$limit": 1},
+            doc! {"$project": {"name": 1, "_id": 0}},
+        ]), [doc! {"name": "edsger"}]);
+        assert_eq!(aggregate(&client, vec![
+            doc! {"$match": {"_id": 1}},
+            doc! {"$addFields": {"city": "$address.city"}},
+            doc! {"$set": {"copy": "$name"}},
+            doc! {"$unset": ["tags", "address"]},
+        ]), [doc! {"_id": 1, "name": "ada", "age": 36, "city": "london", "copy": "ada"}]);
+        assert_eq!(aggregate(&client, vec![doc! {"$match": {"age": {"$gt": 40}}}, doc! {"$count": "n"}]), [doc! {"n": 3}]);
+        assert_eq!(aggregate(&client, vec![doc! {"$match": {"age": {"$gt": 100}}}, doc! {"$count": "n"}]), Vec::<Document>::new());
+
+        let res = block_on(client.aggregate(collection(), vec![doc! {"$lookup": {}}]));
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn unwind() {
+        let (_, client) = setup(people());
+        let names = |documents: Vec<Document>| documents.iter().map(|x| format!("{}:{}", x.get_i32("_id").unwrap(), x.get("tags").map(|x| x.to_string()).unwrap_or_default())).collect::<Vec<_>>();
+        assert_eq!(names(aggregate(&client, vec![doc! {"$unwind": "$tags"}])), ["1:\"math\"", "1:\"code\"", "2:\"code\"", "2:\"navy\""]);
+        assert_eq!(names(aggregate(&client, vec![doc! {"$unwind": {"path": "$tags", "preserveNullAndEmptyArrays": true}}])),
+            ["1:\"math\"", "1:\"code\"", "2:\"code\"", "2:\"navy\"", "3:[]", "4:"]);
+        let unwound = aggregate(&client, vec![doc! {"$match": {"_id": 2}}, doc! {"$unwind": {"path": "$tags", "includeArrayIndex": "i"}}]);
+        assert_eq!(unwound.iter().map(|x| x.get_i64("i").unwrap()).collect::<Vec<_>>(), [0, 1]);
+    }
+
+    #[test]
+    fn group_accumulators() {
+        let (_, client) = setup(vec![
+            doc! {"_id": 1, "team": "a", "points": 3, "tag": "x"},
+            doc! {"_id": 2, "team": "b", "points": 5, "tag": "y"},
+            doc! {"_id": 3, "team": "a", "points": 4, "tag": "x"},
+            doc! {"_id": 4, "team": "a", "points": 1.5, "tag": "z"},
+        ]);
+        let grouped = aggregate(&client, vec![
+            doc! {"$group": {
+                "_id": "$team",
+                "sum": {"$sum": "$points"},
+                "count": {"$sum": 1},
+                "avg": {"$avg": "$points"},
+                "min": {"$min": "$points"},
+                "max": {"$max": "$points"},
+                "first": {"$first": "$_id"},
+                "last": {"$last": "$_id"},
+                "ids": {"$push": "$_id"},
+                "tags": {"$addToSet": "$tag"},
+            }},
+            doc! {"$sort": {"_id": 1}},
+        ]);
+        assert_eq!(grouped, [
+            doc! {"_id": "a", "sum": 8.5, "count": 3, "avg": 8.5 / 3.0, "min": 1.5, "max": 4, "first": 1, "last": 4, "ids": [1, 3, 4], "tags": ["x", "z"]},
+            doc! {"_id": "b", "sum": 5, "count": 1, "avg": 5.0, "min": 5, "max": 5, "first": 2, "last": 2, "ids": [2], "tags": ["y"]},
+        ]);
+        assert_eq!(aggregate(&client, vec![doc! {"$group": {"_id": null, "n": {"$sum": 1}}}]), [doc! {"_id": null, "n": 4}]);
+    }
+
+    #[test]
+    fn json_responses_are_plain_json() {
+        let mock = Arc::new(MockDataApi::new());
+        let id = ObjectId::new();
+        let date = bson::DateTime::from_millis(1_689_712_345_678);
+        mock.insert_documents(&collection(), [doc! {"_id": id, "date": date, "long": 5_i64, "nested": [{"id": id}]}]);
+        let client = mock.client();
+
+        let found = block_on(client.find_one(collection(), None, None)).unwrap().document.unwrap();
+        assert_eq!(found.get_str("_id"), Ok(id.to_hex().as_str()));
+        assert_eq!(found.get_str("date"), Ok("2023-07-18T20:32:25.678Z"));
+        assert_eq!(found.get("long").and_then(Bson::as_i64).or_else(|| found.get("long").and_then(Bson::as_i32).map(i64::from)), Some(5));
+        assert_eq!(found.get_array("nested").unwrap()[0].as_document().unwrap().get_str("id"), Ok(id.to_hex().as_str()));
+
+        let res = block_on(client.insert_one(collection(), doc! {"name": "ada"})).unwrap();
+        assert!(res.inserted_id.is_some());
+    }
+
+    #[test]
+    fn ejson_responses_keep_types() {
+        let id = ObjectId::new();
+        let (_, client) = setup(vec![doc! {"_id": id, "long": 5_i64}]);
+        let found = block_on(client.find_one(collection(), None, None)).unwrap().document.unwrap();
+        assert_eq!(found, doc! {"_id": id, "long": 5_i64});
+    }
+}
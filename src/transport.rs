@@ -0,0 +1,175 @@
+use std::{fmt::Debug, future::Future, pin::Pin, time::Duration};
+
+use futures_util::{Stream, StreamExt, stream};
+
+use http::{HeaderMap, Method, StatusCode};
+
+use crate::Error;
+
+/// A boxed future, which is `Send` on every target except `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+/// A boxed future, which is `Send` on every target except `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + 'a>>;
+
+/// A boxed stream, which is `Send` on every target except `wasm32`
+#[cfg(not(target_arch = "wasm32"))]
+pub type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + Send + 'a>>;
+/// A boxed stream, which is `Send` on every target except `wasm32`
+#[cfg(target_arch = "wasm32")]
+pub type BoxStream<'a, T> = Pin<Box<dyn Stream<Item = T> + 'a>>;
+
+/// Sends the http requests of a [Client](crate::Client)
+///
+/// Implement this to use another http client (e.g. `web-sys` fetch), a middleware stack or an in-process fake.
+pub trait Transport: Debug + Send + Sync {
+    /// sends the request and returns the response, regardless of its status code
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>>;
+
+    /// waits before a request is retried, see [RetryPolicy](crate::RetryPolicy)
+    ///
+    /// The default implementation doesn't wait, implement it with the timer of your runtime.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+        let _ = duration;
+        Box::pin(std::future::ready(()))
+    }
+
+    /// sends the request and returns the body as a stream of chunks, e.g. for server-sent events
+    ///
+    /// The default implementation waits for the whole body using [Transport::send].
+    fn send_streaming(&self, request: HttpRequest) -> BoxFuture<'_, Result<StreamingResponse, Error>> {
+        Box::pin(async move {
+            let res = self.send(request).await?;
+            Ok(StreamingResponse {
+                status: res.status,
+                headers: res.headers,
+                body: Box::pin(stream::once(async move { Ok(res.body) })),
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A request sent through a [Transport]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone)]
+/// A response received through a [Transport]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// A response received through [Transport::send_streaming]
+pub struct StreamingResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: BoxStream<'static, Result<Vec<u8>, Error>>,
+}
+
+impl Debug for StreamingResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingResponse").field("status", &self.status).field("headers", &self.headers).finish_non_exhaustive()
+    }
+}
+
+impl StreamingResponse {
+    /// waits for the whole body
+    pub async fn collect(self) -> Result<HttpResponse, Error> {
+        let mut body = Vec::new();
+        let mut chunks = self.body;
+        while let Some(chunk) = chunks.next().await {
+            body.extend(chunk?);
+        }
+        Ok(HttpResponse { status: self.status, headers: self.headers, body })
+    }
+}
+
+impl HttpResponse {
+    /// the body as (lossy) utf-8
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// percent-encodes a query parameter, everything but the unreserved characters is encoded
+pub(crate) fn encode_query(value: &str) -> String {
+    value.bytes().map(|x| match x {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (x as char).to_string(),
+        x => format!("%{:02X}", x),
+    }).collect()
+}
+
+/// the default transport, if the `reqwest` feature is enabled
+#[cfg(feature = "reqwest")]
+pub fn default_transport() -> std::sync::Arc<dyn Transport> {
+    std::sync::Arc::new(ReqwestTransport::default())
+}
+
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Clone, Default)]
+/// A [Transport] backed by a [reqwest::Client]
+pub struct ReqwestTransport {
+    pub client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Client> for ReqwestTransport {
+    fn from(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Transport for ReqwestTransport {
+    fn send(&self, request: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+        Box::pin(async move {
+            let mut req = self.client.request(request.method, request.url)
+                .headers(request.headers);
+            if let Some(body) = request.body {
+                req = req.body(body);
+            }
+            let res = req.send()
+                .await.map_err(|x| Error::Transport(format!("Failed to send request: {:?}", x)))?;
+
+            let status = res.status();
+            let headers = res.headers().clone();
+            let body = res.bytes().await.map_err(|x| Error::Transport(format!("Failed to read response: {:?}", x)))?;
+            Ok(HttpResponse { status, headers, body: body.to_vec() })
+        })
+    }
+
+    fn send_streaming(&self, request: HttpRequest) -> BoxFuture<'_, Result<StreamingResponse, Error>> {
+        Box::pin(async move {
+            let mut req = self.client.request(request.method, request.url)
+                .headers(request.headers);
+            if let Some(body) = request.body {
+                req = req.body(body);
+            }
+            let res = req.send()
+                .await.map_err(|x| Error::Transport(format!("Failed to send request: {:?}", x)))?;
+
+            let status = res.status();
+            let headers = res.headers().clone();
+            let body = res.bytes_stream()
+                .map(|x| x.map(|x| x.to_vec()).map_err(|x| Error::Transport(format!("Failed to read response: {:?}", x))));
+            Ok(StreamingResponse { status, headers, body: Box::pin(body) })
+        })
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+    #[cfg(target_arch = "wasm32")]
+    fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+        Box::pin(gloo_timers::future::sleep(duration))
+    }
+}
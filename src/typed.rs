@@ -0,0 +1,226 @@
+use std::marker::PhantomData;
+
+use bson::{Bson, Document};
+use futures_util::{Stream, StreamExt};
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::{Client, Collection, Error, InsertResponse, ReplaceResponse, UpdateResponse, DeleteResponse, Update, ChangeEvent, watch};
+
+#[derive(Debug, Clone)]
+/// A collection, whose documents are converted from and into `T`
+pub struct TypedCollection<T> {
+    client: Client,
+    collection: Collection,
+    _document: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> TypedCollection<T> {
+    /// the collection of the client, see [Client::typed_collection]
+    pub fn new(client: Client, collection: Collection) -> Self {
+        TypedCollection { client, collection, _document: PhantomData }
+    }
+    /// the selected collection
+    pub fn collection(&self) -> &Collection {
+        &self.collection
+    }
+    /// the underlying untyped client
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// # Find a Single Document
+    ///
+    /// see [Client::find_one]; returns `None` if no document matches the filter
+    pub async fn find_one(
+        &self,
+        filter: Option<Document>,
+        projection: Option<Document>
+    ) -> Result<Option<T>, Error> {
+        let res = self.client.find_one(self.collection.clone(), filter, projection).await?;
+        res.document.map(from_document).transpose()
+    }
+    /// # Find Multiple Documents
+    ///
+    /// see [Client::find]
+    pub async fn find(
+        &self,
+        filter: Option<Document>,
+        projection: Option<Document>,
+        sort: Option<Document>,
+        limit: Option<i32>,
+        skip: Option<i32>
+    ) -> Result<Vec<T>, Error> {
+        let res = self.client.find(self.collection.clone(), filter, projection, sort, limit, skip).await?;
+        res.documents.unwrap_or_default().into_iter().map(from_document).collect()
+    }
+    /// # Find Multiple Documents, page by page
+    ///
+    /// see [Client::find_stream]
+    pub fn find_stream(
+        &self,
+        filter: Option<Document>,
+        projection: Option<Document>,
+        sort: Option<Document>,
+        page_size: i32
+    ) -> impl Stream<Item = Result<T, Error>> + '_ {
+        self.client.find_stream(self.collection.clone(), filter, projection, sort, page_size)
+            .map(|x| x.and_then(from_document))
+    }
+    /// # Insert a Single Document
+    ///
+    /// see [Client::insert_one]
+    pub async fn insert_one(
+        &self,
+        document: &T
+    ) -> Result<InsertResponse, Error> {
+        self.client.insert_one(self.collection.clone(), to_document(document)?).await
+    }
+    /// # Insert Multiple Documents
+    ///
+    /// see [Client::insert]
+    pub async fn insert(
+        &self,
+        documents: &[T]
+    ) -> Result<InsertResponse, Error> {
+        let documents = documents.iter().map(to_document).collect::<Result<Vec<_>, _>>()?;
+        self.client.insert(self.collection.clone(), documents).await
+    }
+    /// # Replace a Single Document
+    ///
+    /// see [Client::replace_one]
+    pub async fn replace_one(
+        &self,
+        filter: Document,
+        replacement: &T,
+        upsert: Option<bool>
+    ) -> Result<ReplaceResponse, Error> {
+        self.client.replace_one(self.collection.clone(), filter, to_document(replacement)?, upsert).await
+    }
+    /// # Update a Single Document
+    ///
+    /// see [Client::update_one]
+    pub async fn update_one(
+        &self,
+        filter: Document,
+        update: impl Into<Update>,
+        upsert: Option<bool>
+    ) -> Result<UpdateResponse, Error> {
+        self.client.update_one(self.collection.clone(), filter, update, upsert).await
+    }
+    /// # Update Multiple Documents
+    ///
+    /// see [Client::update]
+    pub async fn update(
+        &self,
+        filter: Document,
+        update: impl Into<Update>,
+        upsert: Option<bool>
+    ) -> Result<UpdateResponse, Error> {
+        self.client.update(self.collection.clone(), filter, update, upsert).await
+    }
+    /// # Delete a Single Document
+    ///
+    /// see [Client::delete_one]
+    pub async fn delete_one(
+        &self,
+        filter: Document
+    ) -> Result<DeleteResponse, Error> {
+        self.client.delete_one(self.collection.clone(), filter).await
+    }
+    /// # Delete Multiple Documents
+    ///
+    /// see [Client::delete]
+    pub async fn delete(
+        &self,
+        filter: Document
+    ) -> Result<DeleteResponse, Error> {
+        self.client.delete(self.collection.clone(), filter).await
+    }
+    /// # Watch the Collection
+    ///
+    /// see [Client::watch]; the full documents of the change events are converted into `T`
+    pub fn watch(
+        &self,
+        filter: Option<Document>,
+        ids: Option<Vec<Bson>>
+    ) -> impl Stream<Item = Result<ChangeEvent<T>, Error>> + '_ {
+        self.client.watch_as(self.collection.clone(), filter, ids, watch::DEFAULT_MAX_RECONNECTS)
+    }
+    /// # Run an Aggregation Pipeline
+    ///
+    /// see [Client::aggregate]; the output documents are converted into `R`, which may differ from the collection type
+    pub async fn aggregate<R: DeserializeOwned>(
+        &self,
+        pipeline: impl Into<Vec<Document>>
+    ) -> Result<Vec<R>, Error> {
+        let res = self.client.aggregate(self.collection.clone(), pipeline).await?;
+        res.documents.into_iter().map(from_document).collect()
+    }
+}
+
+/// converts a value into a document
+pub(crate) fn to_document<T: Serialize>(value: &T) -> Result<Document, Error> {
+    bson::to_document(value).map_err(|x| Error::Serialization(format!("{:?}", x)))
+}
+
+/// converts a document into a value
+pub(crate) fn from_document<T: DeserializeOwned>(document: Document) -> Result<T, Error> {
+    bson::from_document(document).map_err(|x| Error::Deserialization(format!("{:?}", x)))
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use std::sync::Arc;
+
+    use bson::{Bson, doc, oid::ObjectId};
+    use futures_executor::block_on;
+    use serde::{Serialize, Deserialize};
+
+    use crate::{Collection, Update, WireFormat, testing::MockDataApi};
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Person {
+        #[serde(rename = "_id")]
+        id: ObjectId,
+        name: String,
+        age: i64,
+    }
+
+    #[test]
+    fn round_trip() {
+        let mock = Arc::new(MockDataApi::new());
+        let mut client = mock.client();
+        client.wire_format = WireFormat::CanonicalEjson;
+        let people = client.typed_collection::<Person>(Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "people".into() });
+        let ada = Person { id: ObjectId::new(), name: "ada".into(), age: 36 };
+
+        block_on(async {
+            let res = people.insert_one(&ada).await.unwrap();
+            assert_eq!(res.inserted_id, Some(Bson::ObjectId(ada.id)));
+            assert_eq!(people.find_one(Some(doc! { "_id": ada.id }), None).await.unwrap(), Some(ada.clone()));
+
+            let older = Person { age: 37, ..ada.clone() };
+            let res = people.replace_one(doc! { "_id": ada.id }, &older, None).await.unwrap();
+            assert_eq!((res.matched_count, res.modified_count), (1, 1));
+            assert_eq!(people.find_one(Some(doc! { "name": "ada" }), None).await.unwrap(), Some(older.clone()));
+
+            people.update_one(doc! { "_id": ada.id }, Update::new().inc("age", 1_i64), None).await.unwrap();
+            assert_eq!(people.find(None, None, None, None, None).await.unwrap(), [Person { age: 38, ..ada.clone() }]);
+
+            let res = people.delete_one(doc! { "_id": ada.id }).await.unwrap();
+            assert_eq!(res.deleted_count, 1);
+            assert_eq!(people.find_one(Some(doc! { "_id": ada.id }), None).await.unwrap(), None);
+        });
+        assert!(mock.documents(people.collection()).is_empty());
+    }
+
+    #[test]
+    fn documents_of_the_wrong_shape_fail() {
+        let mock = Arc::new(MockDataApi::new());
+        let collection = Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "people".into() };
+        mock.insert_documents(&collection, vec![doc! { "_id": 1, "name": "ada" }]);
+        let people = mock.client().typed_collection::<Person>(collection);
+        let res = block_on(people.find_one(None, None));
+        assert!(matches!(res, Err(crate::Error::Deserialization(..))), "{:?}", res);
+    }
+}
@@ -0,0 +1,258 @@
+use bson::{Bson, Document};
+use serde::{Serialize, Serializer, ser::Error as _};
+
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+/// A [MongoDB Update Expression](https://www.mongodb.com/docs/manual/reference/operator/update/)
+///
+/// Either a document of update operators, built with the methods of this type, or an [aggregation pipeline](https://www.mongodb.com/docs/manual/tutorial/update-documents-with-aggregation-pipeline/).
+/// A `Document` or a `Vec<Document>` of stages is converted with `Update::try_from`, which fails with [Error::Serialization] if it contains something else than operators:
+/// an update without operators would replace the matched document.
+/// An update built without any operator fails the same way, when it's sent.
+pub struct Update(Kind);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Operators(Document),
+    /// the stages and the first operator, which got wrongly added with a builder method
+    Pipeline(Vec<Document>, Option<String>),
+}
+
+impl Default for Update {
+    fn default() -> Self {
+        Update::new()
+    }
+}
+
+impl Update {
+    /// an update without operators, which must be given before sending it
+    pub fn new() -> Self {
+        Update(Kind::Operators(Document::new()))
+    }
+    /// an update using an aggregation pipeline of `$addFields`, `$set`, `$project`, `$unset`, `$replaceRoot` and `$replaceWith` stages
+    ///
+    /// The operator methods like [Update::set] can't be used on it, the update then fails when it's sent.
+    pub fn pipeline(stages: impl Into<Vec<Document>>) -> Self {
+        Update(Kind::Pipeline(stages.into(), None))
+    }
+
+    /// adds the operator; on a pipeline the misuse is recorded and fails the validation
+    fn operator(mut self, operator: &str, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        match &mut self.0 {
+            Kind::Operators(operators) => match operators.get_document_mut(operator) {
+                Ok(fields) => {
+                    fields.insert(field, value);
+                },
+                Err(_) => {
+                    let mut fields = Document::new();
+                    fields.insert(field, value);
+                    operators.insert(operator, fields);
+                },
+            },
+            Kind::Pipeline(_, misused) => {
+                misused.get_or_insert_with(|| operator.to_string());
+            },
+        }
+        self
+    }
+
+    /// sets the field to `value` (`$set`)
+    pub fn set(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.operator("$set", field, value)
+    }
+    /// sets the field to `value`, if the update inserts a document (`$setOnInsert`)
+    pub fn set_on_insert(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.operator("$setOnInsert", field, value)
+    }
+    /// removes the field (`$unset`)
+    pub fn unset(self, field: impl Into<String>) -> Self {
+        self.operator("$unset", field, "")
+    }
+    /// increments the field by `amount` (`$inc`)
+    pub fn inc(self, field: impl Into<String>, amount: impl Into<Bson>) -> Self {
+        self.operator("$inc", field, amount)
+    }
+    /// multiplies the field by `factor` (`$mul`)
+    pub fn mul(self, field: impl Into<String>, factor: impl Into<Bson>) -> Self {
+        self.operator("$mul", field, factor)
+    }
+    /// sets the field to `value`, if `value` is less than the current value (`$min`)
+    pub fn min(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.operator("$min", field, value)
+    }
+    /// sets the field to `value`, if `value` is greater than the current value (`$max`)
+    pub fn max(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.operator("$max", field, value)
+    }
+    /// renames the field (`$rename`)
+    pub fn rename(self, field: impl Into<String>, new_name: impl Into<String>) -> Self {
+        self.operator("$rename", field, new_name.into())
+    }
+    /// sets the field to the current date (`$currentDate`)
+    pub fn current_date(self, field: impl Into<String>) -> Self {
+        self.operator("$currentDate", field, true)
+    }
+    /// sets the field to the current timestamp (`$currentDate` with `$type: "timestamp"`)
+    pub fn current_timestamp(self, field: impl Into<String>) -> Self {
+        let mut kind = Document::new();
+        kind.insert("$type", "timestamp");
+        self.operator("$currentDate", field, kind)
+    }
+    /// appends `value` to the array (`$push`)
+    pub fn push(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.operator("$push", field, value)
+    }
+    /// appends multiple values to the array, see [Push] (`$push` with `$each`)
+    pub fn push_each(self, field: impl Into<String>, push: Push) -> Self {
+        self.operator("$push", field, push.0)
+    }
+    /// appends `value` to the array, unless it's already contained (`$addToSet`)
+    pub fn add_to_set(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.operator("$addToSet", field, value)
+    }
+    /// appends each of `values` to the array, unless it's already contained (`$addToSet` with `$each`)
+    pub fn add_to_set_each<V: Into<Bson>>(self, field: impl Into<String>, values: impl IntoIterator<Item = V>) -> Self {
+        let mut each = Document::new();
+        each.insert("$each", values.into_iter().map(Into::into).collect::<Vec<Bson>>());
+        self.operator("$addToSet", field, each)
+    }
+    /// removes the elements equal to `value` from the array (`$pull`)
+    ///
+    /// `value` may also be a condition, e.g. a [Filter](crate::Filter) over the fields of the elements.
+    pub fn pull(self, field: impl Into<String>, value: impl Into<Bson>) -> Self {
+        self.operator("$pull", field, value)
+    }
+    /// removes the first element of the array (`$pop: -1`)
+    pub fn pop_first(self, field: impl Into<String>) -> Self {
+        self.operator("$pop", field, -1)
+    }
+    /// removes the last element of the array (`$pop: 1`)
+    pub fn pop_last(self, field: impl Into<String>) -> Self {
+        self.operator("$pop", field, 1)
+    }
+
+    /// the update expression, either a document or an array of stages
+    pub fn to_bson(&self) -> Bson {
+        match &self.0 {
+            Kind::Operators(x) => Bson::Document(x.clone()),
+            Kind::Pipeline(x, _) => Bson::Array(x.iter().cloned().map(Bson::Document).collect()),
+        }
+    }
+    /// fails, if the update doesn't consist of update operators or pipeline stages
+    fn validate(&self) -> Result<(), String> {
+        let (kind, documents) = match &self.0 {
+            Kind::Operators(x) => ("update", std::slice::from_ref(x)),
+            Kind::Pipeline(_, Some(operator)) => return Err(format!(
+                "the operator `{}` can't be added to an update pipeline, add a stage instead", operator
+            )),
+            Kind::Pipeline(x, None) if x.is_empty() => return Err("the update pipeline has no stages".into()),
+            Kind::Pipeline(x, None) => ("pipeline stage", x.as_slice()),
+        };
+        for document in documents {
+            if document.is_empty() {
+                return Err(format!("the {} has no operators", kind));
+            }
+            if let Some(field) = document.keys().find(|x| !x.starts_with('$')) {
+                return Err(format!("the {} contains `{}`, which isn't an operator; use replace_one to replace documents", kind, field));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Update {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.validate().map_err(S::Error::custom)?;
+        match &self.0 {
+            Kind::Operators(x) => x.serialize(serializer),
+            Kind::Pipeline(x, _) => x.serialize(serializer),
+        }
+    }
+}
+
+impl TryFrom<Document> for Update {
+    type Error = Error;
+    /// checks that the document consists of update operators
+    fn try_from(value: Document) -> Result<Self, Error> {
+        let update = Update(Kind::Operators(value));
+        update.validate().map_err(Error::Serialization)?;
+        Ok(update)
+    }
+}
+impl TryFrom<Vec<Document>> for Update {
+    type Error = Error;
+    /// checks that the stages consist of operators
+    fn try_from(value: Vec<Document>) -> Result<Self, Error> {
+        let update = Update::pipeline(value);
+        update.validate().map_err(Error::Serialization)?;
+        Ok(update)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The values and modifiers of [Update::push_each]
+pub struct Push(Document);
+
+impl Push {
+    /// appends each of `values` (`$each`)
+    pub fn each<V: Into<Bson>>(values: impl IntoIterator<Item = V>) -> Self {
+        let mut push = Document::new();
+        push.insert("$each", values.into_iter().map(Into::into).collect::<Vec<Bson>>());
+        Push(push)
+    }
+    /// inserts the values at the index instead of appending them (`$position`)
+    pub fn position(mut self, position: i32) -> Self {
+        self.0.insert("$position", position);
+        self
+    }
+    /// sorts the array after pushing, by a direction (`1`/`-1`) or a document of fields (`$sort`)
+    pub fn sort(mut self, sort: impl Into<Bson>) -> Self {
+        self.0.insert("$sort", sort.into());
+        self
+    }
+    /// keeps the first (or with a negative number the last) elements of the array after sorting (`$slice`)
+    pub fn slice(mut self, slice: i32) -> Self {
+        self.0.insert("$slice", slice);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+
+    use super::Update;
+    use crate::Error;
+
+    #[test]
+    fn try_from_accepts_operators() {
+        let update = Update::try_from(doc! { "$set": { "a": 1 }, "$inc": { "b": 2 } }).unwrap();
+        assert_eq!(update, Update::new().set("a", 1).inc("b", 2));
+        assert!(Update::try_from(vec![doc! { "$set": { "a": 1 } }]).is_ok());
+    }
+
+    #[test]
+    fn operators_on_pipelines_fail() {
+        let update = Update::pipeline(vec![doc! { "$set": { "a": 1 } }]).set("b", 2).inc("c", 1);
+        let message = "the operator `$set` can't be added to an update pipeline, add a stage instead";
+        assert_eq!(update.validate(), Err(message.to_string()));
+        let error = serde_json::to_string(&update).unwrap_err();
+        assert!(error.to_string().contains(message), "{}", error);
+    }
+
+    #[test]
+    fn try_from_rejects_replacements() {
+        for (result, message) in [
+            (Update::try_from(doc! {}), "the update has no operators"),
+            (Update::try_from(doc! { "a": 1 }), "the update contains `a`, which isn't an operator; use replace_one to replace documents"),
+            (Update::try_from(Vec::new()), "the update pipeline has no stages"),
+            (Update::try_from(vec![doc! { "a": 1 }]), "the pipeline stage contains `a`, which isn't an operator; use replace_one to replace documents"),
+        ] {
+            match result {
+                Err(Error::Serialization(x)) => assert_eq!(x, message),
+                x => panic!("{:?}", x),
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use base64::Engine;
+use bson::{Bson, Document};
+use http::{Method, StatusCode, header::{HeaderName, HeaderValue}};
+use serde::Deserialize;
+
+use crate::{App, Authentication, Client, Credentials, Error, HttpRequest, HttpResponse, Session, Tokens, auth::bearer_headers, ejson};
+
+#[derive(Debug, Clone)]
+/// A user logged in to an [App]
+///
+/// Clones share the tokens of the user, refreshing them for one updates all of them.
+pub struct User {
+    pub(crate) app: App,
+    pub(crate) id: String,
+    pub(crate) session: Session,
+}
+
+#[allow(unused)]
+impl User {
+    /// the id of the user
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    /// the app the user is logged in to
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+    /// the session holding the tokens of the user
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+    /// a snapshot of the current tokens
+    pub fn tokens(&self) -> Tokens {
+        self.session.tokens()
+    }
+    /// the current access token
+    pub fn access_token(&self) -> String {
+        self.session.access_token()
+    }
+    /// the refresh token, none after the user got logged out
+    pub fn refresh_token(&self) -> Option<String> {
+        self.session.tokens().refresh_token
+    }
+    /// true, if the user is the current user of the app
+    pub fn is_current(&self) -> bool {
+        self.app.current_user().map(|x| x.id == self.id).unwrap_or(false)
+    }
+    /// true, until the user got logged out
+    pub fn is_logged_in(&self) -> bool {
+        self.session.can_refresh()
+    }
+    /// a data api [Client], which runs all actions as this user
+    pub fn client(&self) -> Client {
+        self.app.client(self.session.clone())
+    }
+
+    /// # Refresh the profile
+    ///
+    /// fetches the identities, the type and the metadata fields of the user
+    pub async fn refresh_profile(&self) -> Result<UserProfile, Error> {
+        let res = self.send(Method::GET, "/auth/profile".into(), None).await?;
+        serde_json::from_slice(&res.body).map_err(|x| Error::Deserialization(format!("{:?}", x)))
+    }
+
+    /// # Log out
+    ///
+    /// Revokes the refresh token and forgets the tokens, the user stays in [App::all_users] behind the logged in users until it's removed.
+    /// It's no longer the [App::current_user].
+    pub async fn log_out(&self) -> Result<(), Error> {
+        self.app.resolve_location().await?;
+        match self.session.revoke(&self.app.client_api_url(), self.app.transport.as_ref()).await {
+            Err(x) if !x.is_auth() => return Err(x),
+            _ => {},
+        }
+        let mut tokens = self.session.tokens();
+        tokens.access_token = String::new();
+        tokens.refresh_token = None;
+        self.session.set_tokens(tokens);
+        self.session.persist()?;
+        self.app.demote_user(&self.id)
+    }
+
+    /// # Delete the user
+    ///
+    /// Deletes the user on the server and removes it from the app.
+    pub async fn delete(&self) -> Result<(), Error> {
+        self.send(Method::DELETE, "/auth/delete".into(), None).await?;
+        self.app.forget_user(&self.id)
+    }
+
+    /// # Link credentials
+    ///
+    /// Adds the identity of the credentials to this user, e.g. to turn an anonymous user into an email/password user.
+    /// Returns the refreshed profile.
+    pub async fn link_credentials(&self, credentials: Credentials) -> Result<UserProfile, Error> {
+        let body = serde_json::to_vec(&credentials.payload()).map_err(|x| Error::Serialization(format!("{:?}", x)))?;
+        self.send(Method::POST, format!("/app/{}/auth/providers/{}/login?link=true", self.app.application_id, credentials.provider()), Some(body)).await?;
+        self.refresh_profile().await
+    }
+
+    /// # Custom user data
+    ///
+    /// the custom data of the user, as of the last refresh of the access token; empty if there is none
+    pub fn custom_data(&self) -> Result<Document, Error> {
+        let access_token = self.session.access_token();
+        let payload = access_token.split('.').nth(1)
+            .ok_or_else(|| Error::Deserialization("The access token isn't a JWT".into()))?;
+        let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(payload.trim_end_matches('='))
+            .map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+        let claims: serde_json::Value = serde_json::from_slice(&payload).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+        match claims.get("user_data") {
+            Some(data) if !data.is_null() => match ejson::from_extjson(data.clone())? {
+                Bson::Document(x) => Ok(x),
+                x => Err(Error::Deserialization(format!("The custom user data isn't a document: {:?}", x))),
+            },
+            _ => Ok(Document::new()),
+        }
+    }
+    /// # Refresh the custom user data
+    ///
+    /// refreshes the access token, which contains the custom data, and returns it
+    pub async fn refresh_custom_data(&self) -> Result<Document, Error> {
+        self.app.resolve_location().await?;
+        self.session.refresh(&self.app.client_api_url(), self.app.transport.as_ref()).await?;
+        self.custom_data()
+    }
+
+    /// sends the request to the path of the client api with the access token, refreshing the session once if the token expired
+    async fn send(&self, method: Method, path: String, body: Option<Vec<u8>>) -> Result<HttpResponse, Error> {
+        self.app.resolve_location().await?;
+        let url = format!("{}{}", self.app.client_api_url(), path);
+        let request = || -> Result<HttpRequest, Error> {
+            let mut headers = bearer_headers(&self.session.access_token())?;
+            if body.is_some() {
+                headers.append(HeaderName::from_static("content-type"), HeaderValue::from_static("application/json"));
+            }
+            Ok(HttpRequest { method: method.clone(), url: url.clone(), headers, body: body.clone() })
+        };
+
+        let mut res = self.app.transport.send(request()?).await?;
+        if res.status == StatusCode::UNAUTHORIZED && self.session.can_refresh() {
+            self.session.refresh(&self.app.client_api_url(), self.app.transport.as_ref()).await?;
+            res = self.app.transport.send(request()?).await?;
+        }
+        if !res.status.is_success(){
+            return Err(Error::from_response(&res))
+        }
+        Ok(res)
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Deserialize)]
+/// The profile of a [User], see [User::refresh_profile]
+pub struct UserProfile {
+    pub user_id: String,
+    pub domain_id: Option<String>,
+    /// `normal` for users, `server` for server api keys
+    #[serde(rename = "type")]
+    pub user_type: Option<String>,
+    /// the identities of the auth providers linked to the user
+    #[serde(default)]
+    pub identities: Vec<UserIdentity>,
+    /// the metadata fields of the auth providers
+    #[serde(default)]
+    pub data: ProfileData,
+}
+
+impl UserProfile {
+    /// the provider type of the first identity, e.g. `anon-user` or `local-userpass`
+    pub fn provider_type(&self) -> Option<&str> {
+        self.identities.first().map(|x| x.provider_type.as_str())
+    }
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Deserialize)]
+/// An identity of a [User] at one of the auth providers
+pub struct UserIdentity {
+    /// the id of the user at the provider
+    pub id: String,
+    /// e.g. `anon-user`, `local-userpass`, `api-key` or `custom-token`
+    pub provider_type: String,
+    pub provider_id: Option<String>,
+    pub provider_data: Option<serde_json::Value>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Default, Deserialize)]
+/// The metadata fields of a [UserProfile], which are present depending on the auth providers
+pub struct ProfileData {
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub picture_url: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub gender: Option<String>,
+    pub birthday: Option<String>,
+    pub min_age: Option<String>,
+    pub max_age: Option<String>,
+    /// further fields, e.g. of custom JWT metadata
+    #[serde(flatten)]
+    pub other: HashMap<String, serde_json::Value>,
+}
+
+impl From<User> for Session {
+    fn from(user: User) -> Self {
+        user.session
+    }
+}
+impl From<&User> for Session {
+    fn from(user: &User) -> Self {
+        user.session.clone()
+    }
+}
+impl From<User> for Authentication {
+    fn from(user: User) -> Self {
+        Authentication::Bearer(user.session)
+    }
+}
+
+impl Client {
+    /// a clone of the client, which runs the actions as the given user
+    pub fn as_user(&self, user: &User) -> Client {
+        let mut client = self.clone();
+        client.authentication = Authentication::Bearer(user.session.clone());
+        client
+    }
+}
@@ -0,0 +1,509 @@
+use std::collections::VecDeque;
+
+use base64::Engine;
+use bson::{Bson, Document, Timestamp};
+use futures_util::{Stream, StreamExt, stream};
+use http::{Method, StatusCode, header::{HeaderMap, HeaderName, HeaderValue}};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+
+use crate::{Client, Collection, Authentication, Error, HttpRequest, ejson, transport::{BoxStream, StreamingResponse, encode_query}};
+
+/// how often a dropped connection is reopened in a row, before the stream ends with the error
+///
+/// Before every attempt the stream waits for the backoff of the [RetryPolicy](crate::RetryPolicy) of the client.
+pub const DEFAULT_MAX_RECONNECTS: u32 = 3;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "operationType", rename_all = "camelCase")]
+#[serde(bound(deserialize = "T: DeserializeOwned"))]
+/// A change of a watched collection
+pub enum ChangeEvent<T = Document> {
+    #[serde(rename_all = "camelCase")]
+    Insert {
+        /// the resume token of the event, see [ChangeEvent::id]
+        #[serde(rename = "_id")]
+        id: Bson,
+        document_key: Document,
+        full_document: T,
+        ns: Option<Namespace>,
+        cluster_time: Option<Timestamp>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Update {
+        #[serde(rename = "_id")]
+        id: Bson,
+        document_key: Document,
+        update_description: UpdateDescription,
+        /// only present, if the change stream looks up the full document
+        full_document: Option<T>,
+        ns: Option<Namespace>,
+        cluster_time: Option<Timestamp>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Replace {
+        #[serde(rename = "_id")]
+        id: Bson,
+        document_key: Document,
+        full_document: T,
+        ns: Option<Namespace>,
+        cluster_time: Option<Timestamp>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Delete {
+        #[serde(rename = "_id")]
+        id: Bson,
+        document_key: Document,
+        ns: Option<Namespace>,
+        cluster_time: Option<Timestamp>,
+    },
+    /// the connection got dropped and was reopened, changes made in the meantime are missing from the stream
+    ///
+    /// Reload the watched documents, if they must not be missed.
+    #[serde(skip_deserializing)]
+    Reconnected,
+    /// `drop`, `rename`, `dropDatabase`, `invalidate`, ...
+    #[serde(other)]
+    Other,
+}
+
+impl<T> ChangeEvent<T> {
+    /// the resume token of the event, none for [ChangeEvent::Other] and [ChangeEvent::Reconnected]
+    ///
+    /// App Services doesn't accept a resume token when a change stream is opened,
+    /// so it only identifies the event, [Client::watch] can't resume after it.
+    pub fn id(&self) -> Option<&Bson> {
+        match self {
+            ChangeEvent::Insert { id, .. } | ChangeEvent::Update { id, .. } | ChangeEvent::Replace { id, .. } | ChangeEvent::Delete { id, .. } => Some(id),
+            ChangeEvent::Other | ChangeEvent::Reconnected => None,
+        }
+    }
+    /// the `_id` of the changed document, none for [ChangeEvent::Other] and [ChangeEvent::Reconnected]
+    pub fn document_key(&self) -> Option<&Document> {
+        match self {
+            ChangeEvent::Insert { document_key, .. } | ChangeEvent::Update { document_key, .. } | ChangeEvent::Replace { document_key, .. } | ChangeEvent::Delete { document_key, .. } => Some(document_key),
+            ChangeEvent::Other | ChangeEvent::Reconnected => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+/// the fields changed by an update
+pub struct UpdateDescription {
+    pub updated_fields: Document,
+    pub removed_fields: Vec<String>,
+    #[serde(default)]
+    pub truncated_arrays: Vec<Document>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+/// the database and collection of a change
+pub struct Namespace {
+    pub db: String,
+    pub coll: String,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize)]
+struct WatchRequest {
+    name: &'static str,
+    service: String,
+    arguments: Vec<WatchArguments>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Serialize)]
+struct WatchArguments {
+    database: String,
+    collection: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    filter: Option<Document>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ids: Option<Vec<Bson>>,
+}
+
+struct State<'a> {
+    client: &'a Client,
+    /// the base64 encoded watch request, sent as the `baas_request` query parameter
+    baas_request: String,
+    max_reconnects: u32,
+    /// the number of connections dropped since the last event
+    reconnects: u32,
+    /// true, once a connection got opened
+    connected: bool,
+    connection: Option<BoxStream<'static, Result<Vec<u8>, Error>>>,
+    parser: EventParser,
+    events: VecDeque<SseEvent>,
+    done: bool,
+}
+
+#[allow(unused)]
+impl Client {
+    /// # Watch a Collection
+    ///
+    /// Opens a change stream of the collection, like realm-web's `collection.watch()`.
+    ///
+    /// ### filter
+    /// A [MongoDB Query Filter](https://www.mongodb.com/docs/manual/tutorial/query-documents/) over the change events, e.g. `{"fullDocument.status": "open"}`
+    /// ### ids
+    /// only watch the documents with these `_id`s
+    ///
+    /// A dropped connection is reopened up to [DEFAULT_MAX_RECONNECTS] times in a row, waiting for the backoff of the [RetryPolicy](crate::RetryPolicy) before each attempt.
+    /// App Services doesn't support resume tokens, so changes happening while the connection is down are not delivered:
+    /// every reopened connection is announced with [ChangeEvent::Reconnected], after which the watched documents should be reloaded.
+    /// Reload them as well after an error, if they must not be missed.
+    /// Watching requires [Authentication::Bearer].
+    pub fn watch(
+        &self,
+        collection: Collection,
+        filter: Option<Document>,
+        ids: Option<Vec<Bson>>
+    ) -> impl Stream<Item = Result<ChangeEvent, Error>> + '_ {
+        self.watch_as(collection, filter, ids, DEFAULT_MAX_RECONNECTS)
+    }
+
+    /// # Watch a Collection
+    ///
+    /// like [Client::watch], but converts the full documents into `T` and reconnects up to `max_reconnects` times in a row
+    pub fn watch_as<T: DeserializeOwned>(
+        &self,
+        collection: Collection,
+        filter: Option<Document>,
+        ids: Option<Vec<Bson>>,
+        max_reconnects: u32
+    ) -> impl Stream<Item = Result<ChangeEvent<T>, Error>> + '_ {
+        let req = WatchRequest {
+            name: "watch",
+            service: collection.data_source,
+            arguments: vec![WatchArguments {
+                database: collection.database,
+                collection: collection.collection,
+                filter,
+                ids,
+            }],
+        };
+        let baas_request = ejson::WireFormat::CanonicalEjson.encode(&req)
+            .map(|body| encode_query(&base64::engine::general_purpose::STANDARD.encode(body)));
+
+        let state = match baas_request {
+            Ok(baas_request) => State {
+                client: self,
+                baas_request,
+                max_reconnects,
+                reconnects: 0,
+                connected: false,
+                connection: None,
+                parser: EventParser::default(),
+                events: VecDeque::new(),
+                done: false,
+            },
+            Err(x) => return stream::once(async move { Err(x) }).left_stream(),
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(event) = state.events.pop_front() {
+                    match event.decode::<T>() {
+                        Ok(Some(event)) => {
+                            state.reconnects = 0;
+                            return Some((Ok(event), state));
+                        },
+                        Ok(None) => continue,
+                        Err(x) => {
+                            state.done = true;
+                            return Some((Err(x), state));
+                        },
+                    }
+                }
+                if state.done {
+                    return None;
+                }
+
+                let connection = match &mut state.connection {
+                    Some(x) => x,
+                    None => match state.connect().await {
+                        Ok(x) if state.connected => {
+                            state.connection = Some(x);
+                            return Some((Ok(ChangeEvent::Reconnected), state));
+                        },
+                        Ok(x) => {
+                            state.connected = true;
+                            state.connection.insert(x)
+                        },
+                        Err(x) => match state.reconnect(&x).await {
+                            true => continue,
+                            false => return Some((Err(x), state)),
+                        },
+                    },
+                };
+
+                match connection.next().await {
+                    Some(Ok(chunk)) => {
+                        let events = state.parser.feed(&chunk);
+                        state.events.extend(events);
+                    },
+                    Some(Err(x)) => {
+                        state.connection = None;
+                        if !state.reconnect(&x).await {
+                            return Some((Err(x), state));
+                        }
+                    },
+                    None => {
+                        state.connection = None;
+                        let x = Error::Transport("The change stream was closed".into());
+                        if !state.reconnect(&x).await {
+                            return Some((Err(x), state));
+                        }
+                    },
+                }
+            }
+        }).right_stream()
+    }
+}
+
+impl<'a> State<'a> {
+    /// opens the event stream, refreshing the session once if the access token expired
+    async fn connect(&mut self) -> Result<BoxStream<'static, Result<Vec<u8>, Error>>, Error> {
+        let session = match &self.client.authentication {
+            Authentication::Bearer(session) => session,
+            Authentication::ApiKey(_) => {
+                self.done = true;
+                return Err(Error::Auth { status: None, error: "Watching a collection requires a logged in user".into(), error_code: None });
+            },
+        };
+
+        self.client.resolve_location().await?;
+        let mut res = self.open().await?;
+        if res.status == StatusCode::UNAUTHORIZED && session.can_refresh() {
+            session.refresh(&self.client.client_api_url(), self.client.transport.as_ref()).await?;
+            res = self.open().await?;
+        }
+        if !res.status.is_success() {
+            return Err(Error::from_response(&res.collect().await?));
+        }
+        self.parser = EventParser::default();
+        Ok(res.body)
+    }
+    async fn open(&self) -> Result<StreamingResponse, Error> {
+        let mut header_map = HeaderMap::new();
+        self.client.authentication.append_headers(&mut header_map)?;
+        header_map.append(HeaderName::from_static("accept"), HeaderValue::from_static("text/event-stream"));
+
+        self.client.transport.send_streaming(HttpRequest {
+            method: Method::GET,
+            url: format!("{}/app/{}/functions/call?baas_request={}", self.client.client_api_url(), self.client.application_id, self.baas_request),
+            headers: header_map,
+            body: None,
+        }).await
+    }
+    /// true, if the connection should be reopened after the error, waits for the backoff first
+    async fn reconnect(&mut self, error: &Error) -> bool {
+        self.reconnects += 1;
+        let policy = &self.client.retry_policy;
+        if self.done || !policy.is_transient_error(error) || self.reconnects > self.max_reconnects {
+            self.done = true;
+            return false;
+        }
+        self.client.transport.sleep(policy.backoff(self.reconnects, None)).await;
+        true
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct SseEvent {
+    event: String,
+    data: String,
+}
+
+impl SseEvent {
+    /// decodes the change event; `error` events are turned into [Error::ChangeStream]
+    fn decode<T: DeserializeOwned>(&self) -> Result<Option<ChangeEvent<T>>, Error> {
+        match self.event.as_str() {
+            "" | "message" => {
+                let value: serde_json::Value = serde_json::from_str(&self.data).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+                let event = bson::from_bson(ejson::from_extjson(value)?).map_err(|x| Error::Deserialization(format!("{:?}", x)))?;
+                Ok(Some(event))
+            },
+            "error" => {
+                let body: serde_json::Value = serde_json::from_str(&self.data).unwrap_or(serde_json::Value::String(self.data.clone()));
+                Err(Error::ChangeStream {
+                    error: body.get("error").and_then(|x| x.as_str()).map(|x| x.to_string()).unwrap_or_else(|| self.data.clone()),
+                    error_code: body.get("error_code").and_then(|x| x.as_str()).map(|x| x.to_string()),
+                })
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+/// splits a stream of bytes into [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html)
+struct EventParser {
+    buffer: Vec<u8>,
+    current: SseEvent,
+    has_data: bool,
+}
+
+impl EventParser {
+    fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|x| *x == b'\n') {
+            let line = self.buffer.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                if self.has_data {
+                    events.push(std::mem::take(&mut self.current));
+                }
+                self.current = SseEvent::default();
+                self.has_data = false;
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line, ""),
+            };
+            match field {
+                "event" => self.current.event = value.to_string(),
+                "data" => {
+                    if self.has_data {
+                        self.current.data.push('\n');
+                    }
+                    self.current.data.push_str(value);
+                    self.has_data = true;
+                },
+                _ => {},
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::{Arc, Mutex}, time::Duration};
+
+    use bson::doc;
+    use futures_util::{StreamExt, stream};
+    use http::{HeaderMap, StatusCode};
+
+    use super::ChangeEvent;
+    use crate::{Authentication, Client, Collection, Error, HttpRequest, HttpResponse, RetryPolicy, Session, Transport, transport::{BoxFuture, StreamingResponse}};
+
+    const DELETE: &str = "data: {\"operationType\":\"delete\",\"_id\":{\"_data\":\"2\"},\"documentKey\":{\"_id\":2}}\n\n";
+    const INSERT: &str = "data: {\"operationType\":\"insert\",\"_id\":{\"_data\":\"1\"},\"documentKey\":{\"_id\":1},\"fullDocument\":{\"_id\":1}}\n\n";
+
+    /// the chunks of a response, or the error of opening it
+    type Response = Result<Vec<Result<&'static str, Error>>, Error>;
+
+    #[derive(Debug, Default)]
+    /// answers the watch requests with the scripted chunks of the responses, an error once they run out
+    struct Script {
+        responses: Mutex<Vec<Response>>,
+        sleeps: Mutex<Vec<Duration>>,
+    }
+
+    impl Transport for Script {
+        fn send(&self, _: HttpRequest) -> BoxFuture<'_, Result<HttpResponse, Error>> {
+            Box::pin(async move { Err(Error::Transport("unexpected request".into())) })
+        }
+        fn sleep(&self, duration: Duration) -> BoxFuture<'_, ()> {
+            self.sleeps.lock().unwrap().push(duration);
+            Box::pin(async {})
+        }
+        fn send_streaming(&self, _: HttpRequest) -> BoxFuture<'_, Result<StreamingResponse, Error>> {
+            let next = self.responses.lock().unwrap().pop()
+                .unwrap_or_else(|| Err(Error::Api { status: StatusCode::BAD_REQUEST, error: "done".into(), error_code: None, link: None }));
+            Box::pin(async move {
+                let body = next?;
+                let chunks = body.into_iter().map(|x| x.map(|x| x.as_bytes().to_vec()));
+                Ok(StreamingResponse { status: StatusCode::OK, headers: HeaderMap::new(), body: Box::pin(stream::iter(chunks)) })
+            })
+        }
+    }
+
+    fn client(transport: Arc<Script>) -> Client {
+        Client::new()
+            .application_id("app")
+            .authentication(Authentication::Bearer(Session::new("access", None)))
+            .retry_policy(RetryPolicy::new().jitter(false).build())
+            .transport(transport as Arc<dyn Transport>)
+            .build()
+    }
+
+    fn collection() -> Collection {
+        Collection { data_source: "mongodb-atlas".into(), database: "db".into(), collection: "c".into() }
+    }
+
+    #[test]
+    fn reconnects_after_the_backoff() {
+        let transport = Arc::new(Script::default());
+        // popped from the back: two failed connections, then one delivering an event
+        *transport.responses.lock().unwrap() = vec![
+            Ok(vec![Ok(INSERT)]),
+            Err(Error::Transport("reset".into())),
+            Err(Error::Transport("reset".into())),
+        ];
+        let client = client(transport.clone());
+        let events = futures_executor::block_on(client.watch(collection(), None, None).collect::<Vec<_>>());
+
+        assert!(matches!(events[0], Ok(ChangeEvent::Insert { ref document_key, .. }) if *document_key == doc! { "_id": 1 }));
+        assert!(matches!(events[1], Err(Error::Api { status: StatusCode::BAD_REQUEST, .. })));
+        assert_eq!(events.len(), 2);
+        // the reconnects count restarts after the event
+        assert_eq!(*transport.sleeps.lock().unwrap(), vec![
+            Duration::from_millis(200), Duration::from_millis(400), Duration::from_millis(200),
+        ]);
+    }
+
+    #[test]
+    fn announces_reconnects_after_a_dropped_stream() {
+        let transport = Arc::new(Script::default());
+        // popped from the back: the first connection drops after an event and half of the next one
+        *transport.responses.lock().unwrap() = vec![
+            Ok(vec![Ok(DELETE)]),
+            Ok(vec![Ok(INSERT), Ok("data: {\"operationType\":"), Err(Error::Transport("reset".into()))]),
+        ];
+        let client = client(transport.clone());
+        let events = futures_executor::block_on(client.watch(collection(), None, None).collect::<Vec<_>>());
+
+        assert!(matches!(events[0], Ok(ChangeEvent::Insert { .. })), "{:?}", events);
+        assert!(matches!(events[1], Ok(ChangeEvent::Reconnected)), "{:?}", events);
+        assert!(matches!(events[2], Ok(ChangeEvent::Delete { ref document_key, .. }) if *document_key == doc! { "_id": 2 }), "{:?}", events);
+        assert!(matches!(events[3], Err(Error::Api { status: StatusCode::BAD_REQUEST, .. })), "{:?}", events);
+        assert_eq!(events.len(), 4);
+        assert_eq!(transport.sleeps.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn gives_up_after_max_reconnects() {
+        let transport = Arc::new(Script::default());
+        *transport.responses.lock().unwrap() = (0..5).map(|_| Err(Error::Transport("reset".into()))).collect();
+        let client = client(transport.clone());
+        let events = futures_executor::block_on(client.watch_as::<bson::Document>(collection(), None, None, 2).collect::<Vec<_>>());
+
+        assert!(matches!(events[..], [Err(Error::Transport(_))]));
+        assert_eq!(transport.sleeps.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn error_events_end_the_stream() {
+        let transport = Arc::new(Script::default());
+        *transport.responses.lock().unwrap() = vec![Ok(vec![Ok("event: error\ndata: {\"error\":\"no rule\",\"error_code\":\"NoMatchingRuleFound\"}\n\n")])];
+        let client = client(transport.clone());
+        let events = futures_executor::block_on(client.watch(collection(), None, None).collect::<Vec<_>>());
+
+        match &events[..] {
+            [Err(x @ Error::ChangeStream { error, .. })] => {
+                assert_eq!(error, "no rule");
+                assert_eq!(x.error_code(), Some("NoMatchingRuleFound"));
+            },
+            x => panic!("{:?}", x),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+#![cfg(feature = "derive")]
+
+use bson::{doc, oid::ObjectId};
+use realm_web_rs::Model;
+use serde_derive::{Serialize, Deserialize};
+
+#[allow(unused)]
+#[derive(Serialize, Deserialize, Model)]
+#[model(data_source = "mongodb-atlas", database = "shop", collection = "users")]
+#[serde(rename_all = "camelCase")]
+struct User {
+    #[serde(rename = "_id")]
+    id: ObjectId,
+    first_name: String,
+    #[serde(rename = "years")]
+    age: u32,
+    #[serde(skip)]
+    cached: Option<String>,
+}
+
+#[allow(unused)]
+#[derive(Serialize, Deserialize, Model)]
+#[model(data_source = "mongodb-atlas", database = "shop", collection = "orders")]
+struct Order {
+    #[model(id)]
+    number: i64,
+    #[serde(rename = "collectionName")]
+    r#collection_name: String,
+}
+
+#[test]
+fn derives_the_model_consts() {
+    assert_eq!(User::DATA_SOURCE, "mongodb-atlas");
+    assert_eq!(User::DATABASE, "shop");
+    assert_eq!(User::COLLECTION, "users");
+    assert_eq!(User::ID_FIELD, "_id");
+    assert_eq!(User::FIELDS, &["_id", "firstName", "years"]);
+    assert_eq!(User::collection().collection, "users");
+}
+
+#[test]
+fn derives_the_field_consts() {
+    assert_eq!(User::ID, "_id");
+    assert_eq!(User::FIRST_NAME, "firstName");
+    assert_eq!(User::AGE, "years");
+    assert_eq!(Order::COLLECTION_NAME, "collectionName");
+    assert_eq!(User::projection(), doc! { "_id": 1, "firstName": 1, "years": 1 });
+}
+
+#[test]
+fn id_filter_uses_the_id_field() {
+    assert_eq!(Order::ID_FIELD, "number");
+    assert_eq!(Order::id_filter(7_i64), doc! { "number": 7_i64 });
+    let id = ObjectId::new();
+    assert_eq!(User::id_filter(id), doc! { "_id": id });
+}